@@ -1,5 +1,11 @@
+mod plugin;
+
 use clap::Parser;
-use mdx2md_core::config::Config;
+use mdx2md_core::config::{Config, InputFormat};
+use mdx2md_core::script::ScriptResolver;
+use mdx2md_core::value::Value;
+use mdx2md_core::ElementResolver;
+use plugin::{ResolverChain, WasmPlugin};
 use std::io::{self, Read, Write};
 use std::path::{Path, PathBuf};
 
@@ -22,25 +28,69 @@ struct Cli {
     /// File extension for output files in directory mode (default: "md").
     #[arg(long, default_value = "md")]
     ext: String,
+
+    /// WebAssembly component resolver plugin. Repeatable; tried in order
+    /// after component scripts and before config `plugins`.
+    #[arg(long = "plugin", value_name = "WASM")]
+    plugins: Vec<PathBuf>,
+
+    /// Build target for `[[filters]]`, e.g. `--target platform=linux`.
+    /// Repeatable; overrides `[targets]` in the config.
+    #[arg(long = "target", value_name = "NAME=VALUE")]
+    targets: Vec<String>,
+
+    /// Treat input as HTML documents (same as `input_format = "html"`).
+    #[arg(long)]
+    html: bool,
 }
 
 fn main() {
     let cli = Cli::parse();
 
-    let config = match &cli.config {
-        Some(path) => {
-            let toml_str = std::fs::read_to_string(path).unwrap_or_else(|e| {
-                eprintln!("Error reading config {}: {e}", path.display());
-                std::process::exit(1);
-            });
-            Config::from_toml(&toml_str).unwrap_or_else(|e| {
-                eprintln!("Error parsing config: {e}");
-                std::process::exit(1);
-            })
-        }
+    let mut config = match &cli.config {
+        Some(path) => Config::from_file(path).unwrap_or_else(|e| {
+            eprintln!("Error loading config: {}", e.message);
+            std::process::exit(1);
+        }),
         None => Config::default(),
     };
+    for target in &cli.targets {
+        let Some((name, value)) = target.split_once('=') else {
+            eprintln!("Invalid --target {target}: expected NAME=VALUE");
+            std::process::exit(1);
+        };
+        config.targets.insert(name.to_string(), Value::String(value.to_string()));
+    }
+    if cli.html {
+        config.options.input_format = InputFormat::Html;
+    }
+
+    // Component scripts (`script_file`) are resolved relative to the config file
+    let config_dir = cli.config.as_deref().and_then(Path::parent);
+    let scripts = ScriptResolver::from_config(&config, config_dir).unwrap_or_else(|e| {
+        eprintln!("Error loading component scripts: {e}");
+        std::process::exit(1);
+    });
 
+    // Config `plugins` are resolved relative to the config file, like scripts
+    let plugin_paths = cli
+        .plugins
+        .iter()
+        .cloned()
+        .chain(config.plugins.iter().map(|p| config_dir.map_or_else(|| p.into(), |dir| dir.join(p))));
+    let mut resolvers: Vec<Box<dyn ElementResolver>> = vec![Box::new(scripts)];
+    for path in plugin_paths {
+        let plugin = WasmPlugin::load(&path).unwrap_or_else(|e| {
+            eprintln!("Error loading plugin {e}");
+            std::process::exit(1);
+        });
+        resolvers.push(Box::new(plugin));
+    }
+    let resolver = ResolverChain(resolvers);
+
+    // Outputs are still written when a conversion reports errors, but the
+    // exit status says so
+    let mut failed = false;
     if cli.input.is_empty() {
         // Stdin mode
         let mut input = String::new();
@@ -48,18 +98,24 @@ fn main() {
             eprintln!("Error reading stdin: {e}");
             std::process::exit(1);
         });
-        let result = convert_or_exit(&input, &config, "<stdin>");
+        let (result, errors) = convert_or_exit(&input, &config, &resolver, "<stdin>");
+        failed |= errors;
         write_output(&result, cli.output.as_deref());
     } else {
-        let files = collect_mdx_files(&cli.input);
+        let extensions: &[&str] = match config.options.input_format {
+            InputFormat::Mdx => &["mdx"],
+            InputFormat::Html => &["html", "htm"],
+        };
+        let files = collect_input_files(&cli.input, extensions);
         if files.is_empty() {
-            eprintln!("No .mdx files found");
+            eprintln!("No .{} files found", extensions[0]);
             std::process::exit(1);
         }
 
         if files.len() == 1 {
             let input = read_file(&files[0]);
-            let result = convert_or_exit(&input, &config, &files[0].display().to_string());
+            let (result, errors) = convert_or_exit(&input, &config, &resolver, &files[0].display().to_string());
+            failed |= errors;
             write_output(&result, cli.output.as_deref());
         } else {
             let out_dir = cli.output.unwrap_or_else(|| {
@@ -72,14 +128,9 @@ fn main() {
             });
             for file in &files {
                 let input = read_file(file);
-                let result = convert_or_exit(&input, &config, &file.display().to_string());
-                let out_name = file
-                    .file_stem()
-                    .unwrap()
-                    .to_string_lossy()
-                    .to_string()
-                    + "."
-                    + &cli.ext;
+                let (result, errors) = convert_or_exit(&input, &config, &resolver, &file.display().to_string());
+                failed |= errors;
+                let out_name = format!("{}.{}", file.file_stem().unwrap().to_string_lossy(), cli.ext);
                 let out_path = out_dir.join(out_name);
                 std::fs::write(&out_path, &result).unwrap_or_else(|e| {
                     eprintln!("Error writing {}: {e}", out_path.display());
@@ -89,13 +140,22 @@ fn main() {
             }
         }
     }
+    if failed {
+        std::process::exit(1);
+    }
 }
 
-fn convert_or_exit(input: &str, config: &Config, source: &str) -> String {
-    mdx2md_core::convert(input, config).unwrap_or_else(|e| {
+/// The Markdown, and whether any error diagnostics were reported.
+fn convert_or_exit(input: &str, config: &Config, resolver: &dyn ElementResolver, source: &str) -> (String, bool) {
+    let result = mdx2md_core::convert_with_element_resolver(input, config, resolver).unwrap_or_else(|e| {
         eprintln!("Error converting {source}: {e}");
         std::process::exit(1);
-    })
+    });
+    for diagnostic in &result.diagnostics {
+        eprintln!("{source}: {diagnostic}");
+    }
+    let errors = result.has_errors();
+    (result.markdown, errors)
 }
 
 fn read_file(path: &Path) -> String {
@@ -125,14 +185,14 @@ fn write_output(content: &str, output: Option<&Path>) {
     }
 }
 
-fn collect_mdx_files(inputs: &[PathBuf]) -> Vec<PathBuf> {
+fn collect_input_files(inputs: &[PathBuf], extensions: &[&str]) -> Vec<PathBuf> {
     let mut files = Vec::new();
     for input in inputs {
         if input.is_dir() {
             if let Ok(entries) = std::fs::read_dir(input) {
                 for entry in entries.flatten() {
                     let path = entry.path();
-                    if path.extension().and_then(|e| e.to_str()) == Some("mdx") {
+                    if path.extension().and_then(|e| e.to_str()).is_some_and(|e| extensions.contains(&e)) {
                         files.push(path);
                     }
                 }
@@ -0,0 +1,262 @@
+//! Component resolvers loaded from WebAssembly modules.
+//!
+//! A plugin is a core WASM module with no imports (so it has no filesystem,
+//! network or clock access) that exports:
+//!
+//! - `memory`: its linear memory
+//! - `mdx2md_alloc(len: i32) -> i32`: allocate `len` bytes for the request
+//! - `mdx2md_resolve(ptr: i32, len: i32) -> i64`: render one component
+//! - `mdx2md_dealloc(ptr: i32, len: i32)` (optional): free a buffer
+//!
+//! The request is UTF-8 JSON:
+//! `{"tag": "...", "props": {...}, "children": "...", "context": {...}}`
+//! where `context` has `parent`, `ancestors`, `depth`, `index` and `count`.
+//! `mdx2md_resolve` returns `(ptr << 32) | len` of the UTF-8 output, or `-1`
+//! to fall back to the config rules. Each call is limited by fuel and the
+//! memory is capped.
+
+use mdx2md_core::value::Value;
+use mdx2md_core::{ElementResolver, JsxElement, RenderContext, ResolveContext, ResolveError};
+use std::cell::RefCell;
+use std::path::Path;
+use wasmi::{Engine, Instance, Linker, Memory, Module, Store, StoreLimits, StoreLimitsBuilder, TypedFunc};
+
+/// Instructions a plugin may execute per component.
+const FUEL_PER_CALL: u64 = 50_000_000;
+/// Maximum linear memory a plugin may grow to.
+const MAX_MEMORY_BYTES: usize = 64 << 20;
+
+pub struct WasmPlugin {
+    name: String,
+    state: RefCell<PluginState>,
+}
+
+struct PluginState {
+    store: Store<StoreLimits>,
+    memory: Memory,
+    alloc: TypedFunc<i32, i32>,
+    resolve: TypedFunc<(i32, i32), i64>,
+    dealloc: Option<TypedFunc<(i32, i32), ()>>,
+}
+
+impl WasmPlugin {
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let bytes = std::fs::read(path).map_err(|e| format!("{}: {e}", path.display()))?;
+        Self::from_bytes(&path.display().to_string(), &bytes)
+    }
+
+    pub fn from_bytes(name: &str, bytes: &[u8]) -> Result<Self, String> {
+        let mut config = wasmi::Config::default();
+        config.consume_fuel(true);
+        let engine = Engine::new(&config);
+        let module = Module::new(&engine, bytes).map_err(|e| format!("{name}: {e}"))?;
+
+        let limits = StoreLimitsBuilder::new().memory_size(MAX_MEMORY_BYTES).build();
+        let mut store = Store::new(&engine, limits);
+        store.limiter(|limits| limits);
+        store.set_fuel(FUEL_PER_CALL).map_err(|e| format!("{name}: {e}"))?;
+
+        // An empty linker: modules that import anything fail to instantiate
+        let linker = Linker::<StoreLimits>::new(&engine);
+        let instance: Instance = linker
+            .instantiate(&mut store, &module)
+            .and_then(|pre| pre.start(&mut store))
+            .map_err(|e| format!("{name}: {e}"))?;
+
+        let memory = instance
+            .get_memory(&store, "memory")
+            .ok_or_else(|| format!("{name}: missing `memory` export"))?;
+        let alloc = instance
+            .get_typed_func::<i32, i32>(&store, "mdx2md_alloc")
+            .map_err(|e| format!("{name}: `mdx2md_alloc`: {e}"))?;
+        let resolve = instance
+            .get_typed_func::<(i32, i32), i64>(&store, "mdx2md_resolve")
+            .map_err(|e| format!("{name}: `mdx2md_resolve`: {e}"))?;
+        let dealloc = instance.get_typed_func::<(i32, i32), ()>(&store, "mdx2md_dealloc").ok();
+
+        Ok(Self {
+            name: name.to_string(),
+            state: RefCell::new(PluginState {
+                store,
+                memory,
+                alloc,
+                resolve,
+                dealloc,
+            }),
+        })
+    }
+
+    fn call(&self, request: &[u8]) -> Result<Option<String>, String> {
+        let mut guard = self.state.borrow_mut();
+        let state = &mut *guard;
+        state.store.set_fuel(FUEL_PER_CALL).map_err(|e| e.to_string())?;
+
+        let len = i32::try_from(request.len()).map_err(|_| "request too large".to_string())?;
+        let ptr = state.alloc.call(&mut state.store, len).map_err(|e| e.to_string())?;
+        let result = match state.memory.write(&mut state.store, ptr as u32 as usize, request) {
+            Ok(()) => state.resolve.call(&mut state.store, (ptr, len)).map_err(|e| e.to_string()),
+            Err(e) => Err(format!("writing request: {e}")),
+        };
+        // Free the request even when the plugin trapped, so a long-running
+        // process doesn't leak guest memory on every failure
+        if let Some(dealloc) = &state.dealloc {
+            state.store.set_fuel(FUEL_PER_CALL).map_err(|e| e.to_string())?;
+            dealloc.call(&mut state.store, (ptr, len)).map_err(|e| e.to_string())?;
+        }
+        let packed = result?;
+        if packed < 0 {
+            return Ok(None);
+        }
+
+        let out_ptr = (packed >> 32) as u32 as usize;
+        let out_len = (packed & 0xFFFF_FFFF) as u32 as usize;
+        let mut output = vec![0u8; out_len];
+        state
+            .memory
+            .read(&state.store, out_ptr, &mut output)
+            .map_err(|e| format!("reading output: {e}"))?;
+        if let Some(dealloc) = &state.dealloc {
+            dealloc
+                .call(&mut state.store, (out_ptr as i32, out_len as i32))
+                .map_err(|e| e.to_string())?;
+        }
+        String::from_utf8(output)
+            .map(Some)
+            .map_err(|_| "output is not valid UTF-8".to_string())
+    }
+}
+
+impl ElementResolver for WasmPlugin {
+    fn resolve(&self, element: &JsxElement<'_>, cx: &ResolveContext<'_>) -> Result<Option<String>, ResolveError> {
+        let props: serde_json::Map<String, serde_json::Value> = cx
+            .props()
+            .iter()
+            .map(|(k, v)| (k.clone(), to_json(v)))
+            .collect();
+        let request = serde_json::json!({
+            "tag": element.tag,
+            "props": props,
+            "children": cx.render_children(),
+            "context": context_json(cx.context()),
+        });
+        self.call(request.to_string().as_bytes())
+            .map_err(|e| ResolveError::new(format!("plugin {}: {e}", self.name)))
+    }
+}
+
+/// Tries each resolver in order until one handles the element.
+pub struct ResolverChain(pub Vec<Box<dyn ElementResolver>>);
+
+impl ElementResolver for ResolverChain {
+    fn resolve(&self, element: &JsxElement<'_>, cx: &ResolveContext<'_>) -> Result<Option<String>, ResolveError> {
+        for resolver in &self.0 {
+            if let Some(rendered) = resolver.resolve(element, cx)? {
+                return Ok(Some(rendered));
+            }
+        }
+        Ok(None)
+    }
+}
+
+fn context_json(context: &RenderContext) -> serde_json::Value {
+    serde_json::json!({
+        "parent": context.parent(),
+        "ancestors": context.ancestors,
+        "depth": context.depth(),
+        "index": context.index,
+        "count": context.sibling_count,
+    })
+}
+
+fn to_json(value: &Value) -> serde_json::Value {
+    match value {
+        Value::Null => serde_json::Value::Null,
+        Value::Bool(b) => (*b).into(),
+        Value::Number(n) => serde_json::Number::from_f64(*n).map_or(serde_json::Value::Null, Into::into),
+        Value::String(s) | Value::Expression(s) => s.clone().into(),
+        Value::Array(items) => items.iter().map(to_json).collect(),
+        Value::Object(entries) => entries.iter().map(|(k, v)| (k.clone(), to_json(v))).collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mdx2md_core::config::Config;
+
+    /// Renders tags starting with `P` as "from plugin" and falls back otherwise.
+    /// The request JSON starts with `{"tag":"`, so the tag's first byte is at +8.
+    const ECHO_PLUGIN: &str = r#"
+(module
+  (memory (export "memory") 1)
+  (global $next (mut i32) (i32.const 1024))
+  (data (i32.const 16) "from plugin")
+  (func (export "mdx2md_alloc") (param $len i32) (result i32)
+    (local $ptr i32)
+    (local.set $ptr (global.get $next))
+    (global.set $next (i32.add (global.get $next) (local.get $len)))
+    (local.get $ptr))
+  (func (export "mdx2md_resolve") (param $ptr i32) (param $len i32) (result i64)
+    (if (result i64) (i32.eq (i32.load8_u (i32.add (local.get $ptr) (i32.const 8))) (i32.const 80))
+      (then (i64.or (i64.shl (i64.const 16) (i64.const 32)) (i64.const 11)))
+      (else (i64.const -1)))))
+"#;
+
+    fn plugin(wat: &str) -> Result<WasmPlugin, String> {
+        WasmPlugin::from_bytes("test", &wat::parse_str(wat).unwrap())
+    }
+
+    #[test]
+    fn test_plugin_resolves_and_falls_back() {
+        let config = Config::from_toml("[components._default]\ntemplate = \"[{children}]\"").unwrap();
+        let plugin = plugin(ECHO_PLUGIN).unwrap();
+        let result = mdx2md_core::convert_with_element_resolver("<Plug /> <Other>x</Other>", &config, &plugin).unwrap();
+        assert_eq!(result.markdown, "from plugin [x]\n");
+        assert!(result.diagnostics.is_empty());
+    }
+
+    #[test]
+    fn test_plugin_out_of_fuel_is_reported() {
+        let looping = r#"
+(module
+  (memory (export "memory") 1)
+  (func (export "mdx2md_alloc") (param i32) (result i32) (i32.const 0))
+  (func (export "mdx2md_resolve") (param i32 i32) (result i64)
+    (loop $l (br $l))
+    (i64.const -1)))
+"#;
+        let plugin = plugin(looping).unwrap();
+        let result = mdx2md_core::convert_with_element_resolver("<Spin>x</Spin>", &Config::default(), &plugin).unwrap();
+        assert_eq!(result.markdown, "x\n");
+        assert_eq!(result.diagnostics.len(), 1);
+        assert!(result.diagnostics[0].message.contains("plugin test"));
+    }
+
+    #[test]
+    fn test_request_freed_when_plugin_traps() {
+        // `mdx2md_dealloc` records the freed length at address 0
+        let trapping = r#"
+(module
+  (memory (export "memory") 1)
+  (func (export "mdx2md_alloc") (param i32) (result i32) (i32.const 64))
+  (func (export "mdx2md_resolve") (param i32 i32) (result i64) unreachable)
+  (func (export "mdx2md_dealloc") (param $ptr i32) (param $len i32)
+    (i32.store (i32.const 0) (local.get $len))))
+"#;
+        let plugin = plugin(trapping).unwrap();
+        let result = mdx2md_core::convert_with_element_resolver("<Boom />", &Config::default(), &plugin).unwrap();
+        assert_eq!(result.diagnostics.len(), 1);
+        let state = plugin.state.borrow();
+        assert_ne!(state.memory.data(&state.store)[0], 0);
+    }
+
+    #[test]
+    fn test_plugin_imports_rejected() {
+        let with_import = r#"
+(module
+  (import "wasi_snapshot_preview1" "fd_write" (func (param i32 i32 i32 i32) (result i32)))
+  (memory (export "memory") 1))
+"#;
+        assert!(plugin(with_import).is_err());
+    }
+}
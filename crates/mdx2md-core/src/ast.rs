@@ -1,3 +1,7 @@
+use crate::value::{parse_literal, Value};
+use std::collections::HashMap;
+use std::ops::Range;
+
 #[derive(Debug, Clone, PartialEq)]
 pub enum MdxNode {
     Frontmatter(String),
@@ -12,9 +16,75 @@ pub enum MdxNode {
         attributes: Vec<Attribute>,
         children: Vec<MdxNode>,
         self_closing: bool,
+        /// Byte range of the element in [`MdxDocument::source`], when it was
+        /// parsed from MDX.
+        span: Option<Range<usize>>,
     },
 }
 
+impl MdxNode {
+    /// Borrow this node as a [`JsxElement`] view, if it is one.
+    pub fn as_element(&self) -> Option<JsxElement<'_>> {
+        match self {
+            MdxNode::JsxElement {
+                tag,
+                attributes,
+                children,
+                self_closing,
+                ..
+            } => Some(JsxElement {
+                tag,
+                attributes,
+                children,
+                self_closing: *self_closing,
+            }),
+            _ => None,
+        }
+    }
+}
+
+/// A borrowed view of an [`MdxNode::JsxElement`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct JsxElement<'a> {
+    pub tag: &'a str,
+    pub attributes: &'a [Attribute],
+    pub children: &'a [MdxNode],
+    pub self_closing: bool,
+}
+
+impl JsxElement<'_> {
+    /// Typed value of a prop. When an attribute is repeated, the last one wins.
+    pub fn prop(&self, name: &str) -> Option<Value> {
+        self.attributes
+            .iter()
+            .rev()
+            .find(|a| a.name == name)
+            .map(Attribute::typed_value)
+    }
+
+    /// All props as typed values.
+    pub fn props(&self) -> HashMap<String, Value> {
+        self.attributes
+            .iter()
+            .map(|a| (a.name.clone(), a.typed_value()))
+            .collect()
+    }
+
+    /// Child JSX elements, skipping Markdown and expressions.
+    pub fn child_elements(&self) -> impl Iterator<Item = JsxElement<'_>> {
+        self.children.iter().filter_map(MdxNode::as_element)
+    }
+
+    /// Child JSX elements with their index in `children`, as used by
+    /// [`ResolveContext::render_descendant`](crate::ResolveContext::render_descendant).
+    pub fn indexed_child_elements(&self) -> impl Iterator<Item = (usize, JsxElement<'_>)> {
+        self.children
+            .iter()
+            .enumerate()
+            .filter_map(|(i, node)| node.as_element().map(|e| (i, e)))
+    }
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub struct Attribute {
     pub name: String,
@@ -27,8 +97,33 @@ pub enum AttrValue {
     Expression(String),
 }
 
+impl Attribute {
+    /// The typed value of this attribute. A bare attribute (`<Modal open />`)
+    /// is `true`.
+    pub fn typed_value(&self) -> Value {
+        match &self.value {
+            Some(v) => v.to_value(),
+            None => Value::Bool(true),
+        }
+    }
+}
+
+impl AttrValue {
+    /// Parse literal expressions (`{[1, 2]}`, `{true}`, `{{ a: 1 }}`) into typed
+    /// values. Non-literal expressions are kept as [`Value::Expression`].
+    pub fn to_value(&self) -> Value {
+        match self {
+            AttrValue::String(s) => Value::String(s.clone()),
+            AttrValue::Expression(e) => parse_literal(e).unwrap_or_else(|| Value::Expression(e.clone())),
+        }
+    }
+}
+
 /// A flat document is a sequence of top-level nodes.
 #[derive(Debug, Clone, PartialEq)]
 pub struct MdxDocument {
     pub nodes: Vec<MdxNode>,
+    /// The MDX the nodes were parsed from; empty for documents built
+    /// another way (e.g. from HTML).
+    pub source: String,
 }
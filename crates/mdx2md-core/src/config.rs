@@ -1,14 +1,95 @@
+use crate::matcher::ComponentMatcher;
+use crate::value::Value;
 use serde::Deserialize;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
 
 #[derive(Debug, Clone, Deserialize, Default)]
 pub struct Config {
+    /// Bundled rule sets to start from (`docusaurus`, `nextra`, `mintlify`,
+    /// `starlight`). Rules in this config override them.
+    #[serde(default)]
+    pub preset: Vec<String>,
     #[serde(default)]
     pub options: Options,
     #[serde(default)]
     pub components: HashMap<String, ComponentTransform>,
     #[serde(default)]
     pub markdown: MarkdownRewrites,
+    /// WebAssembly resolver plugins, relative to the config file. Loaded by
+    /// the CLI; ignored by the library.
+    #[serde(default)]
+    pub plugins: Vec<String>,
+    /// Reusable template snippets, included with `{>name}`.
+    #[serde(default)]
+    pub partials: HashMap<String, Partial>,
+    /// Values for `expression_handling = "resolve"`, e.g. `{latestVersion}`.
+    #[serde(default)]
+    pub variables: HashMap<String, Value>,
+    /// Data for templates (`{$data.glossary[id]}`) and expressions
+    /// (`{glossary.rsc}`), inline or loaded from JSON, TOML or YAML files.
+    #[serde(default)]
+    pub data: HashMap<String, DataSource>,
+    /// Build target values (`platform = "linux"`) checked by `filters`.
+    #[serde(default)]
+    pub targets: HashMap<String, Value>,
+    /// Rules that keep or drop content depending on `targets`.
+    #[serde(default)]
+    pub filters: Vec<FilterRule>,
+    /// Placeholder text and per-identifier rules for expressions.
+    #[serde(default)]
+    pub expressions: ExpressionRules,
+    /// Content extraction for `input_format = "html"`.
+    #[serde(default)]
+    pub html: HtmlInput,
+}
+
+/// A partial template, inline or loaded from a file relative to the config.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum Partial {
+    Inline(String),
+    File { file: String },
+}
+
+/// A `[data]` entry. Files are relative to the config and loaded by
+/// [`Config::from_file`]; the format follows the extension.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum DataSource {
+    File { file: String },
+    Inline(Value),
+}
+
+/// Keep or drop content depending on a build target: elements matching
+/// `component` by their `prop`, or whole documents by a `frontmatter` key.
+/// The value is compared with `targets[target]` (default: the prop or key
+/// name); rules whose target isn't set don't apply.
+#[derive(Debug, Clone, Deserialize)]
+pub struct FilterRule {
+    /// Tag pattern (name, glob or `/regex/`).
+    #[serde(default)]
+    pub component: Option<String>,
+    /// Prop holding the value(s) to compare. Without one, matching
+    /// elements are kept or dropped unconditionally.
+    #[serde(default)]
+    pub prop: Option<String>,
+    #[serde(default)]
+    pub frontmatter: Option<String>,
+    #[serde(default)]
+    pub target: Option<String>,
+    #[serde(default)]
+    pub action: FilterAction,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum FilterAction {
+    /// Keep content whose value matches the target, drop the rest
+    #[default]
+    Keep,
+    /// Drop content whose value matches the target
+    Drop,
 }
 
 #[derive(Debug, Clone, Deserialize)]
@@ -19,8 +100,26 @@ pub struct Options {
     pub strip_exports: bool,
     #[serde(default = "default_strip")]
     pub expression_handling: ExpressionHandling,
+    /// What `resolve` and `evaluate` do with expressions they can't handle.
+    #[serde(default = "default_strip")]
+    pub unresolved_expressions: ExpressionHandling,
     #[serde(default = "default_true")]
     pub preserve_frontmatter: bool,
+    /// How prop values are escaped in templates, unless a placeholder picks
+    /// its own mode (e.g. `{href:url}`).
+    #[serde(default)]
+    pub template_escape: Escape,
+    /// Convert standard HTML elements (`h1`, `ul`, `a`, `table`, ...) to
+    /// Markdown when no component rule other than `_default` matches.
+    #[serde(default)]
+    pub html_to_markdown: bool,
+    /// Parse input as MDX or as a full HTML document. HTML input always
+    /// converts elements as if `html_to_markdown` were on.
+    #[serde(default)]
+    pub input_format: InputFormat,
+    /// Tags whose children are kept verbatim instead of being parsed as MDX.
+    #[serde(default = "default_raw_text_tags")]
+    pub raw_text_tags: Vec<String>,
 }
 
 impl Default for Options {
@@ -29,7 +128,12 @@ impl Default for Options {
             strip_imports: true,
             strip_exports: true,
             expression_handling: ExpressionHandling::Strip,
+            unresolved_expressions: ExpressionHandling::Strip,
             preserve_frontmatter: true,
+            template_escape: Escape::Markdown,
+            html_to_markdown: false,
+            input_format: InputFormat::Mdx,
+            raw_text_tags: default_raw_text_tags(),
         }
     }
 }
@@ -40,11 +144,196 @@ pub enum ExpressionHandling {
     Strip,
     PreserveRaw,
     Placeholder,
+    /// Look up identifiers and member paths (`{version}`,
+    /// `{frontmatter.title}`) in `[variables]`, frontmatter and exports
+    Resolve,
+    /// Evaluate literals, arithmetic, string concatenation, template
+    /// literals and ternaries over the same variables; no function calls
+    Evaluate,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum InputFormat {
+    #[default]
+    Mdx,
+    Html,
+}
+
+/// Which part of an HTML document to convert.
+#[derive(Debug, Clone, Deserialize)]
+pub struct HtmlInput {
+    /// Element holding the content (`main`, `#content`, `div.article`).
+    /// Defaults to the first `<main>`, `<article>` or `<body>`.
+    #[serde(default)]
+    pub selector: Option<String>,
+    /// Elements removed before conversion.
+    #[serde(default = "default_drop")]
+    pub drop: Vec<String>,
+}
+
+impl Default for HtmlInput {
+    fn default() -> Self {
+        Self {
+            selector: None,
+            drop: default_drop(),
+        }
+    }
+}
+
+impl ExpressionHandling {
+    /// Whether this mode reads document variables.
+    pub(crate) fn uses_scope(&self) -> bool {
+        matches!(self, Self::Resolve | Self::Evaluate)
+    }
+
+    /// Lower is stricter; when several identifier rules match, the strictest
+    /// one applies.
+    pub(crate) fn restrictiveness(&self) -> u8 {
+        match self {
+            Self::Strip => 0,
+            Self::Placeholder => 1,
+            Self::Resolve => 2,
+            Self::Evaluate => 3,
+            Self::PreserveRaw => 4,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Deserialize)]
+pub struct ExpressionRules {
+    /// Text for `placeholder` mode; `{expr}` is the expression source.
+    #[serde(default = "default_placeholder")]
+    pub placeholder: String,
+    /// Handling for expression props (`href={url}`). Unset keeps them as
+    /// written, or resolves them in `resolve`/`evaluate` mode.
+    #[serde(default)]
+    pub attributes: Option<ExpressionHandling>,
+    /// Handling for expressions that reference a variable or member path,
+    /// e.g. `version = "preserve_raw"` or `"process.env" = "strip"`.
+    /// Overrides both text and attribute handling.
+    #[serde(default)]
+    pub identifiers: HashMap<String, ExpressionHandling>,
+}
+
+impl Default for ExpressionRules {
+    fn default() -> Self {
+        Self {
+            placeholder: default_placeholder(),
+            attributes: None,
+            identifiers: HashMap::new(),
+        }
+    }
+}
+
+/// Escaping applied to a prop value substituted into a template.
+#[derive(Debug, Clone, Copy, Deserialize, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum Escape {
+    /// Inline Markdown: punctuation and line-start markers are escaped
+    #[default]
+    Markdown,
+    /// Inside `[...]`: like `markdown`, on a single line
+    LinkText,
+    /// A link or image destination; unsafe schemes become empty
+    Url,
+    /// A code span
+    Code,
+    /// A table cell: like `link_text`, with `|` escaped
+    Cell,
+    /// Verbatim
+    Raw,
+}
+
+#[derive(Debug, Clone, Deserialize, Default)]
 pub struct ComponentTransform {
+    #[serde(default)]
+    pub mode: ComponentMode,
+    /// Extra tag patterns (names, globs, `/regex/`) this rule applies to,
+    /// in addition to its key.
+    #[serde(default, rename = "match")]
+    pub matches: Vec<String>,
+    /// Output template, used when `mode = "template"`.
+    #[serde(default)]
     pub template: String,
+    /// Path to the template, relative to the config file. Loaded by
+    /// [`Config::from_file`] and takes precedence over `template`.
+    #[serde(default)]
+    pub template_file: Option<String>,
+    /// Render this component with another component's rule.
+    #[serde(default)]
+    pub alias: Option<String>,
+    /// Rhai script that renders the component (requires the `scripting` feature).
+    /// Returning `()` falls back to `template`.
+    #[serde(default)]
+    pub script: Option<String>,
+    /// Path to a Rhai script, relative to the config file.
+    #[serde(default)]
+    pub script_file: Option<String>,
+    /// Values for props the element doesn't set.
+    #[serde(default)]
+    pub defaults: HashMap<String, Value>,
+    /// Prop renames applied before anything else (`variant = "type"`). A
+    /// prop already set under the new name wins.
+    #[serde(default)]
+    pub rename: BTreeMap<String, String>,
+    /// Props that must be set (or defaulted). A missing one is reported as an
+    /// error, and the element is replaced by its children.
+    #[serde(default)]
+    pub required: Vec<String>,
+    /// Per-prop value replacements (`map.type = { warning = "Warning" }`).
+    #[serde(default)]
+    pub map: HashMap<String, HashMap<String, String>>,
+    /// Set on rules merged from a preset. They are only matched when no rule
+    /// of the config itself (key, `match` pattern or glob) matches.
+    #[serde(skip)]
+    pub from_preset: bool,
+}
+
+impl ComponentTransform {
+    /// Apply `rename`, `defaults` and `map` to an element's props. Returns the
+    /// props together with the names of missing `required` props.
+    pub fn prepare_props(&self, mut props: HashMap<String, Value>) -> (HashMap<String, Value>, Vec<&str>) {
+        for (from, to) in &self.rename {
+            if let Some(value) = props.remove(from) {
+                props.entry(to.clone()).or_insert(value);
+            }
+        }
+        for (name, value) in &self.defaults {
+            props.entry(name.clone()).or_insert_with(|| value.clone());
+        }
+        for (name, replacements) in &self.map {
+            if let Some(value) = props.get_mut(name) {
+                if let Some(replacement) = replacements.get(&value.to_string()) {
+                    *value = Value::String(replacement.clone());
+                }
+            }
+        }
+        let missing = self
+            .required
+            .iter()
+            .filter(|name| !props.contains_key(name.as_str()))
+            .map(String::as_str)
+            .collect();
+        (props, missing)
+    }
+}
+
+/// How a matched component is rendered.
+#[derive(Debug, Clone, Copy, Deserialize, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum ComponentMode {
+    /// Render `template` with props and children substituted.
+    #[default]
+    Template,
+    /// Remove the element and its children.
+    Drop,
+    /// Replace the element with its rendered children.
+    Unwrap,
+    /// Re-serialize the element and its props as HTML around the rendered children.
+    Html,
+    /// Emit the element's MDX source unchanged.
+    Raw,
 }
 
 #[derive(Debug, Clone, Deserialize)]
@@ -59,6 +348,17 @@ pub struct MarkdownRewrites {
     pub strip_html_comments: bool,
     #[serde(default = "default_true")]
     pub strip_doctype: bool,
+    /// Turn `:::note` container directives into blockquotes. Unset follows
+    /// the presets.
+    #[serde(default)]
+    pub admonitions: Option<bool>,
+    #[serde(default)]
+    pub headings: Option<HeadingRewrite>,
+    /// What to do with `{#custom-id}` heading ids.
+    #[serde(default)]
+    pub heading_ids: HeadingIds,
+    #[serde(default)]
+    pub toc: Option<TocRewrite>,
 }
 
 impl Default for MarkdownRewrites {
@@ -69,6 +369,10 @@ impl Default for MarkdownRewrites {
             images: None,
             strip_html_comments: false,
             strip_doctype: true,
+            admonitions: None,
+            headings: None,
+            heading_ids: HeadingIds::Anchor,
+            toc: None,
         }
     }
 }
@@ -86,6 +390,65 @@ pub enum TableFormat {
     List,
 }
 
+/// `[markdown.headings]`: renumber headings so converted docs nest when
+/// they're concatenated or embedded.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct HeadingRewrite {
+    /// Added to every heading level, clamped to 1..=6.
+    #[serde(default)]
+    pub shift: i32,
+    /// Insert `# title` at the top (after frontmatter) and demote every
+    /// other heading one more level.
+    #[serde(default)]
+    pub title: Option<String>,
+    /// Keep the first H1 and turn later H1s into H2s.
+    #[serde(default)]
+    pub single_h1: bool,
+    /// Write Setext headings (`Title` / `===`) as ATX (`# Title`).
+    #[serde(default)]
+    pub setext_to_atx: bool,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum HeadingIds {
+    /// Remove the id and point in-page links at the heading's GitHub slug.
+    Strip,
+    /// Leave `{#id}` for renderers that support it.
+    Keep,
+    /// Replace the id with an `<a id="...">` anchor inside the heading, so
+    /// links from other pages keep working.
+    #[default]
+    Anchor,
+}
+
+/// `[markdown.toc]`: a linked outline of the document's headings.
+#[derive(Debug, Clone, Deserialize)]
+pub struct TocRewrite {
+    /// Shallowest heading level listed.
+    #[serde(default = "default_toc_min_depth")]
+    pub min_depth: u8,
+    /// Deepest heading level listed.
+    #[serde(default = "default_toc_max_depth")]
+    pub max_depth: u8,
+    #[serde(default)]
+    pub position: TocPosition,
+}
+
+/// Where the table of contents goes when the document has no `[[toc]]`
+/// marker.
+#[derive(Debug, Clone, Copy, Deserialize, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum TocPosition {
+    /// After the frontmatter and an opening H1.
+    Top,
+    /// Directly after the frontmatter.
+    AfterFrontmatter,
+    /// Only at a marker.
+    #[default]
+    Marker,
+}
+
 #[derive(Debug, Clone, Deserialize)]
 pub struct LinkRewrite {
     #[serde(default)]
@@ -112,18 +475,305 @@ fn default_true() -> bool {
     true
 }
 
+fn default_toc_min_depth() -> u8 {
+    2
+}
+
+fn default_toc_max_depth() -> u8 {
+    3
+}
+
 fn default_strip() -> ExpressionHandling {
     ExpressionHandling::Strip
 }
 
+fn default_placeholder() -> String {
+    "[expression]".to_string()
+}
+
+fn default_raw_text_tags() -> Vec<String> {
+    crate::tokenizer::DEFAULT_RAW_TEXT_TAGS.iter().map(|t| t.to_string()).collect()
+}
+
+fn default_drop() -> Vec<String> {
+    ["head", "script", "style", "noscript", "template", "iframe", "nav", "footer"]
+        .map(String::from)
+        .to_vec()
+}
+
 fn default_preserve() -> TableFormat {
     TableFormat::Preserve
 }
 
+fn parse_data(path: &Path, text: &str) -> Result<Value, String> {
+    match path.extension().and_then(|e| e.to_str()) {
+        Some("json") => serde_json::from_str(text).map_err(|e| e.to_string()),
+        Some("toml") => toml::from_str(text).map_err(|e| e.to_string()),
+        Some("yaml" | "yml") => serde_yaml::from_str(text).map_err(|e| e.to_string()),
+        _ => Err("unknown format, expected .json, .toml, .yaml or .yml".to_string()),
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConfigError {
+    pub message: String,
+}
+
+impl std::fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Config error: {}", self.message)
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+/// Deepest partial nesting allowed, to catch include cycles.
+const MAX_PARTIAL_DEPTH: usize = 16;
+
 impl Config {
+    /// Parse a config. Partials and aliases are resolved; `template_file` is
+    /// rejected, and file partials need [`Config::from_file`].
     pub fn from_toml(input: &str) -> Result<Self, toml::de::Error> {
-        toml::from_str(input)
+        let custom = |e: ConfigError| <toml::de::Error as serde::de::Error>::custom(e.message);
+        check_templates(&toml::from_str(input)?).map_err(custom)?;
+        let mut config: Config = toml::from_str(input)?;
+        if let Some(name) = config
+            .components
+            .iter()
+            .filter(|(_, rule)| rule.template_file.is_some())
+            .map(|(name, _)| name)
+            .min()
+        {
+            return Err(custom(ConfigError {
+                message: format!("component {name} sets `template_file`, which needs `Config::from_file`"),
+            }));
+        }
+        config.unescape_newlines();
+        config.link().map_err(custom)?;
+        Ok(config)
+    }
+
+    /// Read and parse a config file, loading `template_file` and file
+    /// partials relative to the file's directory.
+    pub fn from_file(path: &Path) -> Result<Self, ConfigError> {
+        let read = |path: &Path| {
+            std::fs::read_to_string(path).map_err(|e| ConfigError {
+                message: format!("reading {}: {e}", path.display()),
+            })
+        };
+        let text = read(path)?;
+        let to_error = |e: toml::de::Error| ConfigError {
+            message: format!("{}: {e}", path.display()),
+        };
+        check_templates(&toml::from_str(&text).map_err(to_error)?)?;
+        let mut config: Config = toml::from_str(&text).map_err(to_error)?;
+        config.unescape_newlines();
+
+        let base_dir = path.parent().unwrap_or(Path::new(""));
+        for partial in config.partials.values_mut() {
+            if let Partial::File { file } = partial {
+                *partial = Partial::Inline(read(&base_dir.join(&*file))?);
+            }
+        }
+        for (name, source) in config.data.iter_mut() {
+            if let DataSource::File { file } = source {
+                let path = base_dir.join(&*file);
+                let value = parse_data(&path, &read(&path)?).map_err(|e| ConfigError {
+                    message: format!("data source {name} ({}): {e}", path.display()),
+                })?;
+                *source = DataSource::Inline(value);
+            }
+        }
+        for rule in config.components.values_mut() {
+            if let Some(file) = &rule.template_file {
+                rule.template = read(&base_dir.join(file))?;
+            }
+        }
+
+        config.link()?;
+        Ok(config)
+    }
+
+    /// Turn literal `\n` in inline templates into newlines, for TOML literal
+    /// strings. Templates read from files are used as written.
+    fn unescape_newlines(&mut self) {
+        let unescape = |text: &mut String| *text = text.replace("\\n", "\n");
+        for rule in self.components.values_mut() {
+            unescape(&mut rule.template);
+        }
+        for partial in self.partials.values_mut() {
+            if let Partial::Inline(text) = partial {
+                unescape(text);
+            }
+        }
+        unescape(&mut self.expressions.placeholder);
+    }
+
+    /// Loaded `[data]` sources as one object, keyed by name. Sources that
+    /// are still file references (not loaded through `from_file`) are skipped.
+    pub fn data_value(&self) -> Value {
+        Value::Object(self.data_entries())
+    }
+
+    pub(crate) fn data_entries(&self) -> Vec<(String, Value)> {
+        let mut entries: Vec<(String, Value)> = self
+            .data
+            .iter()
+            .filter_map(|(name, source)| match source {
+                DataSource::Inline(value) => Some((name.clone(), value.clone())),
+                DataSource::File { .. } => None,
+            })
+            .collect();
+        entries.sort_by(|a, b| a.0.cmp(&b.0));
+        entries
+    }
+
+    /// Merge presets, expand partials in templates, copy aliased rules and
+    /// check patterns. The loaders do this; a config built in code (as the
+    /// WASM bindings do) must call it once before use.
+    pub fn link(&mut self) -> Result<(), ConfigError> {
+        crate::preset::apply(self)?;
+
+        // Scripts run through `script::ScriptResolver`, which needs the feature
+        #[cfg(not(feature = "scripting"))]
+        if let Some(name) = self
+            .components
+            .iter()
+            .filter(|(_, rule)| rule.script.is_some() || rule.script_file.is_some())
+            .map(|(name, _)| name)
+            .min()
+        {
+            return Err(ConfigError {
+                message: format!("component {name} uses a script, which needs the `scripting` feature"),
+            });
+        }
+
+        // `from_file` has replaced file sources with their contents
+        if let Some((name, file)) = self
+            .data
+            .iter()
+            .filter_map(|(name, source)| match source {
+                DataSource::File { file } => Some((name, file)),
+                DataSource::Inline(_) => None,
+            })
+            .min()
+        {
+            return Err(ConfigError {
+                message: format!("data source {name} ({file}) needs Config::from_file"),
+            });
+        }
+
+        for (name, rule) in self.components.iter_mut() {
+            rule.template = expand_partials(&rule.template, &self.partials, 0)
+                .map_err(|message| ConfigError {
+                    message: format!("in component {name}: {message}"),
+                })?;
+        }
+
+        let aliased: Vec<String> = self
+            .components
+            .iter()
+            .filter(|(_, rule)| rule.alias.is_some())
+            .map(|(name, _)| name.clone())
+            .collect();
+        for name in aliased {
+            let target = self.alias_target(&name)?;
+            let mut rule = self.components[&target].clone();
+            let alias = self.components.get_mut(&name).unwrap();
+            rule.matches = std::mem::take(&mut alias.matches);
+            rule.from_preset = alias.from_preset;
+            rule.alias = None;
+            self.components.insert(name, rule);
+        }
+
+        // Surface invalid component patterns (e.g. a bad regex) at load time
+        ComponentMatcher::from_components(&self.components).map_err(|e| ConfigError { message: e.message })?;
+        ComponentMatcher::from_presets(&self.components).map_err(|e| ConfigError { message: e.message })?;
+        crate::targets::TargetFilter::new(self).map_err(|e| ConfigError { message: e.message })?;
+        Ok(())
+    }
+
+    /// Follow a chain of aliases to the rule that renders the component.
+    fn alias_target(&self, name: &str) -> Result<String, ConfigError> {
+        let mut seen = vec![name];
+        let mut current = name;
+        while let Some(next) = self.components[current].alias.as_deref() {
+            if !self.components.contains_key(next) {
+                return Err(ConfigError {
+                    message: format!("component {current} is an alias of unknown component {next}"),
+                });
+            }
+            if seen.contains(&next) {
+                return Err(ConfigError {
+                    message: format!("alias cycle: {} -> {next}", seen.join(" -> ")),
+                });
+            }
+            seen.push(next);
+            current = next;
+        }
+        Ok(current.to_string())
+    }
+}
+
+/// Reject `template` mode rules that don't say what to render, and alias
+/// rules with settings of their own. `template` defaults to empty, so a
+/// missing or misspelled key would otherwise drop the component silently.
+fn check_templates(config: &toml::Table) -> Result<(), ConfigError> {
+    let Some(toml::Value::Table(components)) = config.get("components") else {
+        return Ok(());
+    };
+    for (name, rule) in components {
+        let Some(rule) = rule.as_table() else { continue };
+        // An alias renders with its target's rule, so its own settings would
+        // be ignored
+        if let Some(target) = rule.get("alias").and_then(toml::Value::as_str) {
+            if let Some(key) = rule.keys().find(|key| !matches!(key.as_str(), "alias" | "match")) {
+                return Err(ConfigError {
+                    message: format!("component {name} is an alias of {target} and can't set `{key}`"),
+                });
+            }
+        }
+        let template_mode = rule.get("mode").is_none_or(|mode| mode.as_str() == Some("template"));
+        let renders = ["template", "template_file", "alias", "script", "script_file"]
+            .iter()
+            .any(|key| rule.contains_key(*key));
+        if template_mode && !renders {
+            return Err(ConfigError {
+                message: format!("component {name} has no template (set `template`, `template_file` or `mode`)"),
+            });
+        }
+    }
+    Ok(())
+}
+
+/// Replace `{>name}` with the partial's template, recursively.
+fn expand_partials(template: &str, partials: &HashMap<String, Partial>, depth: usize) -> Result<String, String> {
+    if !template.contains("{>") {
+        return Ok(template.to_string());
+    }
+    if depth >= MAX_PARTIAL_DEPTH {
+        return Err("partials nested too deeply (is there a cycle?)".to_string());
+    }
+
+    let mut out = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(open) = rest.find("{>") {
+        out.push_str(&rest[..open]);
+        let after = &rest[open + 2..];
+        let Some(close) = after.find('}') else {
+            out.push_str(&rest[open..]);
+            return Ok(out);
+        };
+        let name = after[..close].trim();
+        match partials.get(name) {
+            Some(Partial::Inline(partial)) => out.push_str(&expand_partials(partial, partials, depth + 1)?),
+            Some(Partial::File { file }) => return Err(format!("partial {name} ({file}) needs Config::from_file")),
+            None => return Err(format!("unknown partial {name}")),
+        }
+        rest = &after[close + 1..];
     }
+    out.push_str(rest);
+    Ok(out)
 }
 
 #[cfg(test)]
@@ -144,6 +794,7 @@ mod tests {
         assert!(config.components.contains_key("CodeBlock"));
         assert!(config.components.contains_key("Badge"));
         assert!(config.components.contains_key("_default"));
+        assert_eq!(config.components["Callout"].mode, ComponentMode::Template);
 
         let tables = config.markdown.tables.unwrap();
         assert_eq!(tables.format, TableFormat::List);
@@ -156,4 +807,110 @@ mod tests {
         assert!(images.make_absolute);
         assert_eq!(images.base_url, "https://cdn.example.com");
     }
+
+    #[test]
+    fn test_component_modes() {
+        let config = Config::from_toml(
+            r#"
+[components.details]
+mode = "html"
+
+[components.Hidden]
+mode = "drop"
+
+[components.Wrapper]
+mode = "unwrap"
+"#,
+        )
+        .unwrap();
+        assert_eq!(config.components["details"].mode, ComponentMode::Html);
+        assert_eq!(config.components["Hidden"].mode, ComponentMode::Drop);
+        assert_eq!(config.components["Wrapper"].mode, ComponentMode::Unwrap);
+        assert!(config.components["Wrapper"].template.is_empty());
+    }
+
+    #[test]
+    fn test_component_patterns() {
+        let config = Config::from_toml(
+            r#"
+[components."Icon*"]
+template = ""
+
+[components.admonition]
+match = ["Note", "Tip"]
+template = "> {children}"
+"#,
+        )
+        .unwrap();
+        assert_eq!(config.components["admonition"].matches, vec!["Note", "Tip"]);
+
+        let err = Config::from_toml("[components.\"/(bad/\"]\ntemplate = \"\"\n").unwrap_err();
+        assert!(err.to_string().contains("invalid component pattern"));
+
+        let err = Config::from_toml("[components.Card]\ntemplte = \"{title}\"\n").unwrap_err();
+        assert!(err.to_string().contains("component Card has no template"));
+    }
+
+    #[test]
+    #[cfg(not(feature = "scripting"))]
+    fn test_scripts_need_feature() {
+        let err = Config::from_toml("[components.Card]\nscript = \"props.title\"\n").unwrap_err();
+        assert!(err.to_string().contains("component Card uses a script"));
+    }
+
+    #[test]
+    fn test_from_file_templates_partials_and_aliases() {
+        let config = Config::from_file(Path::new("tests/fixtures/templates/mdx2md.toml")).unwrap();
+        let callout = &config.components["Callout"];
+        assert_eq!(callout.template, "> **{type}**: {children}\n>\n> _See the docs._\n");
+        let admonition = &config.components["Admonition"];
+        assert_eq!(admonition.template, callout.template);
+        assert_eq!(admonition.defaults, callout.defaults);
+        assert_eq!(admonition.matches, vec!["Tip"]);
+        assert_eq!(config.components["Code"].template, "```c\nprintf(\"{text}\\n\");\n```\n");
+    }
+
+    #[test]
+    fn test_data_sources() {
+        let config = Config::from_file(Path::new("tests/fixtures/data/mdx2md.toml")).unwrap();
+        let data = config.data_value();
+        assert_eq!(data.get_path("versions.latest").map(Value::to_string).as_deref(), Some("3.1.0"));
+        assert_eq!(data.get_path("site.name").map(Value::to_string).as_deref(), Some("Example Docs"));
+
+        let input = "<Term id=\"rsc\" />\n\n<ApiParams endpoint=\"users\" />\n";
+        assert_eq!(
+            crate::convert(input, &config).unwrap(),
+            "**rsc**: React Server Components, rendered on the server\n\n\
+             | name | type | description |\n| --- | --- | --- |\n\
+             | id | string | The user id |\n| limit | number | Page size, \\`1 \\| 100\\` |\n"
+        );
+
+        let err = Config::from_toml("[data]\nx = { file = \"x.json\" }").unwrap_err();
+        assert!(err.to_string().contains("data source x (x.json) needs Config::from_file"), "{err}");
+    }
+
+    #[test]
+    fn test_rename_keeps_prop_set_under_new_name() {
+        // `<Callout variant="tip" type="warning">` keeps `type`, so a preset's
+        // rename never overwrites what the author wrote
+        let config = Config::from_toml("[components.Callout]\ntemplate = \"{type}\"\nrename = { variant = \"type\" }").unwrap();
+        let props = HashMap::from([
+            ("variant".to_string(), Value::String("tip".to_string())),
+            ("type".to_string(), Value::String("warning".to_string())),
+        ]);
+        let (props, _) = config.components["Callout"].prepare_props(props);
+        assert_eq!(props, HashMap::from([("type".to_string(), Value::String("warning".to_string()))]));
+    }
+
+    #[test]
+    fn test_alias_and_partial_errors() {
+        assert!(Config::from_toml("[components.A]\nalias = \"B\"").is_err());
+        let err = Config::from_toml("[components.A]\nalias = \"B\"\ndefaults = { x = 1 }\n[components.B]\ntemplate = \"b\"").unwrap_err();
+        assert!(err.to_string().contains("component A is an alias of B and can't set `defaults`"), "{err}");
+        let err = Config::from_toml("[components.A]\ntemplate_file = \"a.md\"").unwrap_err();
+        assert!(err.to_string().contains("needs `Config::from_file`"), "{err}");
+        assert!(Config::from_toml("[components.A]\nalias = \"B\"\n[components.B]\nalias = \"A\"").is_err());
+        assert!(Config::from_toml("[components.A]\ntemplate = \"{>missing}\"").is_err());
+        assert!(Config::from_toml("[partials]\na = \"{>a}\"\n[components.A]\ntemplate = \"{>a}\"").is_err());
+    }
 }
@@ -0,0 +1,48 @@
+use std::fmt;
+
+/// A problem found while converting a document, such as a resolver
+/// rejecting malformed component usage.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    /// Tag of the JSX element the diagnostic is about, if any.
+    pub tag: Option<String>,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Warning,
+    Error,
+}
+
+impl Diagnostic {
+    pub fn warning(tag: Option<&str>, message: impl Into<String>) -> Self {
+        Self {
+            severity: Severity::Warning,
+            tag: tag.map(str::to_string),
+            message: message.into(),
+        }
+    }
+
+    pub fn error(tag: Option<&str>, message: impl Into<String>) -> Self {
+        Self {
+            severity: Severity::Error,
+            tag: tag.map(str::to_string),
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let severity = match self.severity {
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        };
+        match &self.tag {
+            Some(tag) => write!(f, "{severity}: <{tag}>: {}", self.message),
+            None => write!(f, "{severity}: {}", self.message),
+        }
+    }
+}
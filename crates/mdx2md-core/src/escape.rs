@@ -0,0 +1,244 @@
+use crate::config::Escape;
+
+impl Escape {
+    /// Parse a template modifier name (`{title:link_text}`).
+    pub(crate) fn from_modifier(name: &str) -> Option<Self> {
+        match name {
+            "markdown" => Some(Self::Markdown),
+            "link_text" => Some(Self::LinkText),
+            "url" => Some(Self::Url),
+            "code" => Some(Self::Code),
+            "cell" => Some(Self::Cell),
+            "raw" => Some(Self::Raw),
+            _ => None,
+        }
+    }
+
+    /// Escape a value for this context.
+    pub(crate) fn apply(self, s: &str) -> String {
+        match self {
+            Self::Markdown => escape_markdown(s),
+            Self::LinkText => escape_markdown(&single_line(s)),
+            Self::Url => escape_url(s),
+            Self::Code => code_span(&single_line(s)),
+            Self::Cell => escape_markdown(&single_line(s)),
+            Self::Raw => s.to_string(),
+        }
+    }
+}
+
+/// Escape inline Markdown syntax so the value renders as literal text.
+/// Each line also has block markers (`#`, `>`, `-`, `1.`, ...) escaped and
+/// leading whitespace removed, so a value can't start a heading, list, quote
+/// or indented code block.
+fn escape_markdown(s: &str) -> String {
+    escape_text(s, true)
+}
+
+/// Escape a value inserted into Markdown text, such as a resolved
+/// expression. Block markers on the first line are only escaped when the
+/// value starts a line (`at_line_start`), so `v{-1}` stays `v-1`.
+pub(crate) fn escape_text(s: &str, at_line_start: bool) -> String {
+    let mut out = String::with_capacity(s.len());
+    for (i, line) in s.split('\n').enumerate() {
+        if i > 0 {
+            out.push('\n');
+            escape_line_start(line.trim_start(), &mut out);
+        } else if at_line_start {
+            escape_line_start(line, &mut out);
+        } else {
+            escape_inline(line, &mut out);
+        }
+    }
+    out
+}
+
+fn escape_line_start(line: &str, out: &mut String) {
+    let digits = line.len() - line.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+    let rest = match line.as_bytes().first() {
+        Some(b'#' | b'-' | b'+' | b'=') => {
+            out.push('\\');
+            out.push_str(&line[..1]);
+            &line[1..]
+        }
+        Some(b'0'..=b'9') if matches!(line.as_bytes().get(digits), Some(b'.' | b')')) => {
+            out.push_str(&line[..digits]);
+            out.push('\\');
+            out.push_str(&line[digits..=digits]);
+            &line[digits + 1..]
+        }
+        _ => line,
+    };
+    escape_inline(rest, out);
+}
+
+fn escape_inline(text: &str, out: &mut String) {
+    for c in text.chars() {
+        if matches!(c, '\\' | '`' | '*' | '_' | '[' | ']' | '<' | '>' | '|' | '{' | '}') {
+            out.push('\\');
+        }
+        out.push(c);
+    }
+}
+
+fn single_line(s: &str) -> String {
+    s.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Percent-encode characters that would end or break a link destination.
+/// Only relative URLs and `http`, `https`, `mailto` and `tel` are allowed.
+fn escape_url(s: &str) -> String {
+    let s = s.trim();
+    // Check the scheme as a renderer reads it, with character references and
+    // percent-escapes decoded and whitespace removed (`javascript&#58;`)
+    let decoded: String = percent_decode(&decode_entities(s))
+        .chars()
+        .filter(|c| !c.is_whitespace() && !c.is_control())
+        .collect();
+    if let Some((scheme, _)) = decoded.split_once(':') {
+        let is_scheme = scheme.starts_with(|c: char| c.is_ascii_alphabetic())
+            && scheme.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.'));
+        let allowed = ["http", "https", "mailto", "tel"];
+        if is_scheme && !allowed.iter().any(|a| scheme.eq_ignore_ascii_case(a)) {
+            return String::new();
+        }
+    }
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        if c.is_ascii_control() || matches!(c, ' ' | '"' | '<' | '>' | '(' | ')' | '\\' | '`') {
+            out.push_str(&format!("%{:02X}", c as u32));
+        } else {
+            out.push(c);
+        }
+    }
+    out
+}
+
+/// Decode `%XX` escapes. Invalid UTF-8 is replaced.
+fn percent_decode(s: &str) -> String {
+    let bytes = s.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = bytes.get(i + 1..i + 3).and_then(|h| std::str::from_utf8(h).ok());
+        match hex.filter(|_| bytes[i] == b'%').and_then(|h| u8::from_str_radix(h, 16).ok()) {
+            Some(b) => {
+                out.push(b);
+                i += 3;
+            }
+            None => {
+                out.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+/// Decode HTML character references: the common named ones and numeric
+/// `&#NN;` / `&#xNN;` forms. Unknown references are left as written.
+pub(crate) fn decode_entities(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('&') {
+        out.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let decoded = rest[1..].find(';').filter(|end| *end <= 10).and_then(|end| {
+            let name = &rest[1..=end];
+            let c = match name {
+                "amp" => Some('&'),
+                "lt" => Some('<'),
+                "gt" => Some('>'),
+                "quot" => Some('"'),
+                "apos" => Some('\''),
+                "nbsp" => Some('\u{a0}'),
+                "ndash" => Some('–'),
+                "mdash" => Some('—'),
+                "hellip" => Some('…'),
+                "lsquo" => Some('‘'),
+                "rsquo" => Some('’'),
+                "ldquo" => Some('“'),
+                "rdquo" => Some('”'),
+                "laquo" => Some('«'),
+                "raquo" => Some('»'),
+                "middot" => Some('·'),
+                "bull" => Some('•'),
+                "copy" => Some('©'),
+                "reg" => Some('®'),
+                "trade" => Some('™'),
+                _ => {
+                    let code = match name.strip_prefix('#') {
+                        Some(hex) if hex.starts_with(['x', 'X']) => u32::from_str_radix(&hex[1..], 16).ok(),
+                        Some(dec) => dec.parse().ok(),
+                        None => None,
+                    };
+                    code.and_then(char::from_u32)
+                }
+            };
+            c.map(|c| (c, end + 2))
+        });
+        match decoded {
+            Some((c, len)) => {
+                out.push(c);
+                rest = &rest[len..];
+            }
+            None => {
+                out.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+/// Wrap text in a code span, using a backtick fence longer than any run of
+/// backticks inside it.
+fn code_span(s: &str) -> String {
+    let longest = s
+        .split(|c| c != '`')
+        .map(str::len)
+        .max()
+        .unwrap_or(0);
+    let fence = "`".repeat(longest + 1);
+    let pad = if s.starts_with('`') || s.ends_with('`') { " " } else { "" };
+    format!("{fence}{pad}{s}{pad}{fence}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_markdown_escape() {
+        assert_eq!(Escape::Markdown.apply("# Admin](https://evil)"), "\\# Admin\\](https://evil)");
+        assert_eq!(Escape::Markdown.apply("a\n  > quote\n1. item"), "a\n\\> quote\n1\\. item");
+        assert_eq!(Escape::Markdown.apply("<script>*x*"), "\\<script\\>\\*x\\*");
+        assert_eq!(Escape::Markdown.apply("a | b"), "a \\| b");
+        assert_eq!(Escape::Markdown.apply("Hi {#x}"), "Hi \\{#x\\}");
+    }
+
+    #[test]
+    fn test_inline_escapes() {
+        assert_eq!(Escape::LinkText.apply("a\n[b]"), "a \\[b\\]");
+        assert_eq!(Escape::Cell.apply("a | b\nc"), "a \\| b c");
+        assert_eq!(Escape::Code.apply("a `b`"), "`` a `b` ``");
+        assert_eq!(Escape::Raw.apply("# x"), "# x");
+    }
+
+    #[test]
+    fn test_url_escape() {
+        assert_eq!(Escape::Url.apply("https://a.dev/x y)"), "https://a.dev/x%20y%29");
+        assert_eq!(Escape::Url.apply("/docs/a_b"), "/docs/a_b");
+        assert_eq!(Escape::Url.apply("JavaScript:alert(1)"), "");
+        assert_eq!(Escape::Url.apply("javascript&#58;alert(1)"), "");
+        assert_eq!(Escape::Url.apply("java&#x09;script&colon;x"), "java&#x09;script&colon;x");
+        assert_eq!(Escape::Url.apply("jav%61script:x"), "");
+        assert_eq!(Escape::Url.apply("/search?a=1&amp;b=%20"), "/search?a=1&amp;b=%20");
+    }
+
+    #[test]
+    fn test_decode_entities() {
+        assert_eq!(decode_entities("a &lt; b &amp;&amp; &#39;c&#x27; &mdash; &bogus; & d"), "a < b && 'c' — &bogus; & d");
+    }
+}
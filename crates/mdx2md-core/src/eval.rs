@@ -0,0 +1,687 @@
+use crate::value::{parse_literal, Value};
+
+/// Evaluate a side-effect-free JS expression: literals, template literals,
+/// arithmetic, string concatenation, comparisons, `&&`/`||`/`??`, ternaries
+/// and lookups into `scope`. Anything else (calls, assignments, unknown
+/// identifiers) returns `None`.
+pub(crate) fn evaluate(src: &str, scope: &Value) -> Option<Value> {
+    let mut eval = Evaluator {
+        chars: src.chars().collect(),
+        pos: 0,
+        scope,
+        depth: 0,
+        skipping: 0,
+    };
+    let value = eval.expression()?;
+    eval.skip_ws();
+    if eval.pos == eval.chars.len() {
+        Some(value)
+    } else {
+        None
+    }
+}
+
+/// Nesting limit, so hostile input can't overflow the stack.
+const MAX_DEPTH: usize = 64;
+
+struct Evaluator<'s> {
+    chars: Vec<char>,
+    pos: usize,
+    scope: &'s Value,
+    depth: usize,
+    /// Above 0 while parsing an operand that isn't evaluated, where failed
+    /// lookups give `null` instead of failing the expression.
+    skipping: usize,
+}
+
+impl Evaluator<'_> {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn skip_ws(&mut self) {
+        while self.peek().is_some_and(char::is_whitespace) {
+            self.pos += 1;
+        }
+    }
+
+    /// Consume `op` if it comes next, and isn't the start of a longer operator.
+    fn eat(&mut self, op: &str) -> bool {
+        self.skip_ws();
+        let len = op.chars().count();
+        let matches = self.chars.len() >= self.pos + len && self.chars[self.pos..self.pos + len].iter().copied().eq(op.chars());
+        if !matches {
+            return false;
+        }
+        let next = self.chars.get(self.pos + len).copied();
+        let longer = match op {
+            "=" | "!" | "<" | ">" | "==" | "!=" => next == Some('='),
+            "&" | "|" | "?" => next == Some(op.chars().next().unwrap_or_default()),
+            "*" => next == Some('*'),
+            _ => false,
+        };
+        if longer || (op == "?" && next == Some('.')) {
+            return false;
+        }
+        self.pos += len;
+        true
+    }
+
+    fn expression(&mut self) -> Option<Value> {
+        self.depth += 1;
+        if self.depth > MAX_DEPTH {
+            return None;
+        }
+        let value = self.ternary();
+        self.depth -= 1;
+        value
+    }
+
+    /// Parse the operand `parse` reads, evaluating it only if `taken`, as
+    /// for the right side of `&&`/`||`/`??` and the branches of a ternary.
+    fn operand(&mut self, taken: bool, parse: impl FnOnce(&mut Self) -> Option<Value>) -> Option<Option<Value>> {
+        if taken {
+            return parse(self).map(Some);
+        }
+        self.skipping += 1;
+        let parsed = parse(self);
+        self.skipping -= 1;
+        parsed.map(|_| None)
+    }
+
+    fn ternary(&mut self) -> Option<Value> {
+        let cond = self.logical()?;
+        if !self.eat("?") {
+            return Some(cond);
+        }
+        let truthy = cond.is_truthy();
+        let then = self.operand(truthy, Self::expression)?;
+        if !self.eat(":") {
+            return None;
+        }
+        let otherwise = self.operand(!truthy, Self::expression)?;
+        then.or(otherwise)
+    }
+
+    /// `||`, `&&` and `??`, left to right with `&&` binding tighter. The
+    /// right side is only evaluated when the operator takes it.
+    fn logical(&mut self) -> Option<Value> {
+        let mut left = self.and()?;
+        loop {
+            let taken = if self.eat("||") {
+                !left.is_truthy()
+            } else if self.eat("??") {
+                left == Value::Null
+            } else {
+                return Some(left);
+            };
+            if let Some(right) = self.operand(taken, Self::and)? {
+                left = right;
+            }
+        }
+    }
+
+    fn and(&mut self) -> Option<Value> {
+        let mut left = self.equality()?;
+        while self.eat("&&") {
+            if let Some(right) = self.operand(left.is_truthy(), Self::equality)? {
+                left = right;
+            }
+        }
+        Some(left)
+    }
+
+    fn equality(&mut self) -> Option<Value> {
+        let mut left = self.relational()?;
+        loop {
+            let (strict, negate) = if self.eat("===") {
+                (true, false)
+            } else if self.eat("==") {
+                (false, false)
+            } else if self.eat("!==") {
+                (true, true)
+            } else if self.eat("!=") {
+                (false, true)
+            } else {
+                return Some(left);
+            };
+            let right = self.relational()?;
+            let equal = if strict { strict_equals(&left, &right) } else { loose_equals(&left, &right) };
+            left = Value::Bool(equal != negate);
+        }
+    }
+
+    fn relational(&mut self) -> Option<Value> {
+        let mut left = self.additive()?;
+        loop {
+            let op = ["<=", ">=", "<", ">"].into_iter().find(|op| self.eat(op));
+            let Some(op) = op else {
+                return Some(left);
+            };
+            let right = self.additive()?;
+            let ordering = match (&left, &right) {
+                (Value::String(a), Value::String(b)) => a.partial_cmp(b),
+                _ => to_number(&left).partial_cmp(&to_number(&right)),
+            };
+            let result = ordering.is_some_and(|o| match op {
+                "<" => o.is_lt(),
+                ">" => o.is_gt(),
+                "<=" => o.is_le(),
+                _ => o.is_ge(),
+            });
+            left = Value::Bool(result);
+        }
+    }
+
+    fn additive(&mut self) -> Option<Value> {
+        let mut left = self.multiplicative()?;
+        loop {
+            if self.eat("+") {
+                let right = self.multiplicative()?;
+                left = match (&left, &right) {
+                    (Value::String(_), _) | (_, Value::String(_)) => {
+                        Value::String(format!("{}{}", to_js_string(&left), to_js_string(&right)))
+                    }
+                    _ => Value::Number(to_number(&left) + to_number(&right)),
+                };
+            } else if self.eat("-") {
+                let right = self.multiplicative()?;
+                left = Value::Number(to_number(&left) - to_number(&right));
+            } else {
+                return Some(left);
+            }
+        }
+    }
+
+    fn multiplicative(&mut self) -> Option<Value> {
+        let mut left = self.unary()?;
+        loop {
+            let op = ["*", "/", "%"].into_iter().find(|op| self.eat(op));
+            let Some(op) = op else {
+                return Some(left);
+            };
+            let right = self.unary()?;
+            let (a, b) = (to_number(&left), to_number(&right));
+            left = Value::Number(match op {
+                "*" => a * b,
+                "/" => a / b,
+                _ => a % b,
+            });
+        }
+    }
+
+    fn unary(&mut self) -> Option<Value> {
+        let op = ["!", "-", "+"].into_iter().find(|op| self.eat(op));
+        let Some(op) = op else {
+            return self.postfix();
+        };
+        self.depth += 1;
+        if self.depth > MAX_DEPTH {
+            return None;
+        }
+        let operand = self.unary()?;
+        self.depth -= 1;
+        Some(match op {
+            "!" => Value::Bool(!operand.is_truthy()),
+            "-" => Value::Number(-to_number(&operand)),
+            _ => Value::Number(to_number(&operand)),
+        })
+    }
+
+    /// Member access: `a.b`, `a?.b`, `a[expr]`, and `.length`.
+    fn postfix(&mut self) -> Option<Value> {
+        let mut value = self.primary()?;
+        loop {
+            self.skip_ws();
+            let optional = self.chars[self.pos..].starts_with(&['?', '.']);
+            if optional || self.peek() == Some('.') {
+                self.pos += if optional { 2 } else { 1 };
+                if optional && self.peek() == Some('[') {
+                    continue;
+                }
+                let name = self.identifier()?;
+                if optional && value == Value::Null {
+                    continue;
+                }
+                value = self.lookup(member(&value, &name))?;
+            } else if self.peek() == Some('[') {
+                self.pos += 1;
+                let key = self.expression()?;
+                if !self.eat("]") {
+                    return None;
+                }
+                let key = match key {
+                    Value::Number(n) if n >= 0.0 && n.fract() == 0.0 => (n as usize).to_string(),
+                    other => to_js_string(&other),
+                };
+                value = self.lookup(member(&value, &key))?;
+            } else if self.peek() == Some('(') {
+                // No function calls
+                return None;
+            } else {
+                return Some(value);
+            }
+        }
+    }
+
+    fn primary(&mut self) -> Option<Value> {
+        self.skip_ws();
+        match self.peek()? {
+            '(' => {
+                self.pos += 1;
+                let value = self.expression()?;
+                self.eat(")").then_some(value)
+            }
+            '`' => self.template_literal().map(Value::String),
+            '[' => self.array(),
+            '"' | '\'' | '{' | '0'..='9' | '.' => self.literal(),
+            _ => {
+                let name = self.identifier()?;
+                match name.as_str() {
+                    "true" => Some(Value::Bool(true)),
+                    "false" => Some(Value::Bool(false)),
+                    "null" | "undefined" => Some(Value::Null),
+                    _ => self.lookup(self.scope.get(&name).cloned()),
+                }
+            }
+        }
+    }
+
+    /// Strings, numbers and object literals, parsed by the literal parser.
+    fn literal(&mut self) -> Option<Value> {
+        let start = self.pos;
+        let end = self.literal_end()?;
+        let src: String = self.chars[start..end].iter().collect();
+        self.pos = end;
+        parse_literal(&src)
+    }
+
+    /// End of the string, number or balanced `{...}` starting here.
+    fn literal_end(&self) -> Option<usize> {
+        let mut pos = self.pos;
+        match self.chars[pos] {
+            q @ ('"' | '\'') => {
+                pos += 1;
+                while *self.chars.get(pos)? != q {
+                    pos += if self.chars[pos] == '\\' { 2 } else { 1 };
+                }
+                Some(pos + 1)
+            }
+            '{' => {
+                let mut depth = 0;
+                let mut quote = None;
+                loop {
+                    let c = *self.chars.get(pos)?;
+                    match (quote, c) {
+                        (Some(_), '\\') => pos += 1,
+                        (Some(q), c) if c == q => quote = None,
+                        (Some(_), _) => {}
+                        (None, '"' | '\'' | '`') => quote = Some(c),
+                        (None, '{') => depth += 1,
+                        (None, '}') => {
+                            depth -= 1;
+                            if depth == 0 {
+                                return Some(pos + 1);
+                            }
+                        }
+                        _ => {}
+                    }
+                    pos += 1;
+                }
+            }
+            _ => {
+                // A sign only belongs to a decimal exponent (`1e-3`, not `0x1e-3`)
+                let hex = matches!(self.chars.get(pos..pos + 2), Some(['0', 'x' | 'X']));
+                while let Some(&c) = self.chars.get(pos) {
+                    let exponent_sign = matches!(c, '+' | '-') && !hex && matches!(self.chars[pos - 1], 'e' | 'E');
+                    if !(c.is_ascii_alphanumeric() || c == '.' || c == '_' || exponent_sign) {
+                        break;
+                    }
+                    pos += 1;
+                }
+                Some(pos)
+            }
+        }
+    }
+
+    fn array(&mut self) -> Option<Value> {
+        self.pos += 1;
+        let mut items = Vec::new();
+        loop {
+            if self.eat("]") {
+                return Some(Value::Array(items));
+            }
+            items.push(self.expression()?);
+            if !self.eat(",") {
+                return self.eat("]").then_some(Value::Array(items));
+            }
+        }
+    }
+
+    fn template_literal(&mut self) -> Option<String> {
+        self.pos += 1;
+        let mut out = String::new();
+        loop {
+            match self.peek()? {
+                '`' => {
+                    self.pos += 1;
+                    return Some(out);
+                }
+                '$' if self.chars.get(self.pos + 1) == Some(&'{') => {
+                    self.pos += 2;
+                    let value = self.expression()?;
+                    if !self.eat("}") {
+                        return None;
+                    }
+                    out.push_str(&to_js_string(&value));
+                }
+                '\\' => {
+                    self.pos += 1;
+                    let c = self.peek()?;
+                    self.pos += 1;
+                    out.push(match c {
+                        'n' => '\n',
+                        't' => '\t',
+                        c => c,
+                    });
+                }
+                c => {
+                    self.pos += 1;
+                    out.push(c);
+                }
+            }
+        }
+    }
+
+    /// A lookup's result; a failed one is `null` in an operand that isn't
+    /// evaluated.
+    fn lookup(&self, value: Option<Value>) -> Option<Value> {
+        value.or_else(|| (self.skipping > 0).then_some(Value::Null))
+    }
+
+    fn identifier(&mut self) -> Option<String> {
+        self.skip_ws();
+        let start = self.pos;
+        while self
+            .peek()
+            .is_some_and(|c| c.is_alphanumeric() || c == '_' || c == '$')
+        {
+            self.pos += 1;
+        }
+        let name: String = self.chars[start..self.pos].iter().collect();
+        let valid = name.chars().next().is_some_and(|c| !c.is_ascii_digit());
+        valid.then_some(name)
+    }
+}
+
+/// Variable references in an expression as dotted paths
+/// (`process.env.SECRET`, `user.name`), for identifier rules. Bracket
+/// access with a string or number key (`process["env"]`) counts as a
+/// segment, and whitespace, comments and closing parentheses inside a chain
+/// are skipped (`(process) .env`). A computed key (`process[key]`) ends the
+/// path with `.*`, as it could be any property. String contents are
+/// skipped; `${...}` inside template literals is not.
+pub(crate) fn identifier_paths(src: &str) -> Vec<String> {
+    let mut paths = Vec::new();
+    let chars: Vec<char> = src.chars().collect();
+    let mut i = 0;
+    let is_start = |c: char| c.is_alphabetic() || c == '_' || c == '$';
+    let is_continue = |c: char| c.is_alphanumeric() || c == '_' || c == '$';
+    while i < chars.len() {
+        let c = chars[i];
+        if c == '"' || c == '\'' {
+            i += 1;
+            while i < chars.len() && chars[i] != c {
+                i += if chars[i] == '\\' { 2 } else { 1 };
+            }
+            i += 1;
+        } else if c == '$' && chars.get(i + 1) == Some(&'{') {
+            i += 2;
+        } else if is_start(c) && (i == 0 || !(is_continue(chars[i - 1]) || chars[i - 1] == '.')) {
+            let mut path = String::new();
+            let ident_end = |i: usize| (i..chars.len()).find(|&j| !is_continue(chars[j])).unwrap_or(chars.len());
+            let end = ident_end(i);
+            path.extend(&chars[i..end]);
+            i = end;
+            loop {
+                let next = skip_trivia(&chars, i, true);
+                match bracket_key(&chars, next) {
+                    Some((Some(key), end)) => {
+                        path.push('.');
+                        path.push_str(&key);
+                        i = end;
+                        continue;
+                    }
+                    Some((None, end)) => {
+                        path.push_str(".*");
+                        i = end;
+                        break;
+                    }
+                    None => {}
+                }
+                let Some(dot) = member_dot(&chars, next) else { break };
+                let start = skip_trivia(&chars, next + dot, false);
+                if !chars.get(start).is_some_and(|c| is_start(*c)) {
+                    break;
+                }
+                let end = ident_end(start);
+                path.push('.');
+                path.extend(&chars[start..end]);
+                i = end;
+            }
+            paths.push(path);
+        } else {
+            i += 1;
+        }
+    }
+    paths
+}
+
+/// Index of the first character at or after `i` that isn't whitespace or a
+/// comment (or, with `parens`, a closing parenthesis).
+fn skip_trivia(chars: &[char], mut i: usize, parens: bool) -> usize {
+    loop {
+        match chars.get(i..i + 2) {
+            Some(['/', '*']) => {
+                i = (i + 2..chars.len())
+                    .find(|&j| chars.get(j..j + 2) == Some(&['*', '/']))
+                    .map_or(chars.len(), |j| j + 2);
+            }
+            Some(['/', '/']) => {
+                i = (i..chars.len()).find(|&j| chars[j] == '\n').unwrap_or(chars.len());
+            }
+            _ => match chars.get(i) {
+                Some(c) if c.is_whitespace() || (parens && *c == ')') => i += 1,
+                _ => return i,
+            },
+        }
+    }
+}
+
+/// Length of a `.` or `?.` member access at `i` (not `?.[`).
+fn member_dot(chars: &[char], i: usize) -> Option<usize> {
+    match chars.get(i..i + 2) {
+        Some(['?', '.']) => Some(2),
+        _ if chars.get(i) == Some(&'.') && chars.get(i + 1) != Some(&'.') => Some(1),
+        _ => None,
+    }
+}
+
+/// Bracket access (`["env"]`, `?.['env']`, `[0]`, `[key]`) starting at `i`,
+/// with its key if it is a plain string or number, and the index after it.
+fn bracket_key(chars: &[char], mut i: usize) -> Option<(Option<String>, usize)> {
+    if chars.get(i..i + 2) == Some(&['?', '.']) {
+        i += 2;
+    }
+    if chars.get(i) != Some(&'[') {
+        return None;
+    }
+    let open = skip_trivia(chars, i + 1, false);
+    let literal = chars.get(open).filter(|c| matches!(c, '"' | '\'' | '`')).and_then(|quote| {
+        let len = chars[open + 1..].iter().position(|c| c == quote || *c == '\\' || *c == '$')?;
+        let end = open + 1 + len;
+        let close = skip_trivia(chars, end + 1, false);
+        (chars[end] == *quote && chars.get(close) == Some(&']')).then(|| (chars[open + 1..end].iter().collect(), close + 1))
+    });
+    // Array indexes (`items[0]`) are plain keys too
+    let index = chars[open..].iter().position(|c| !c.is_ascii_digit()).filter(|len| *len > 0).and_then(|len| {
+        let close = skip_trivia(chars, open + len, false);
+        (chars.get(close) == Some(&']')).then(|| (chars[open..open + len].iter().collect(), close + 1))
+    });
+    match literal.or(index) {
+        Some((key, end)) => Some((Some(key), end)),
+        // Computed: the path stops here, and the key is scanned on its own
+        None => Some((None, i + 1)),
+    }
+}
+
+/// How React renders a value as a child: `null`, `undefined` and booleans
+/// render nothing and arrays are concatenated.
+pub(crate) fn to_jsx_text(value: &Value) -> String {
+    match value {
+        Value::Null | Value::Bool(_) => String::new(),
+        Value::Array(items) => items.iter().map(to_jsx_text).collect(),
+        other => to_js_string(other),
+    }
+}
+
+fn member(value: &Value, name: &str) -> Option<Value> {
+    match (value, name) {
+        (Value::String(s), "length") => Some(Value::Number(s.encode_utf16().count() as f64)),
+        (Value::Array(items), "length") => Some(Value::Number(items.len() as f64)),
+        (Value::Array(_) | Value::Object(_), _) => Some(value.get(name).cloned().unwrap_or(Value::Null)),
+        _ => None,
+    }
+}
+
+fn strict_equals(a: &Value, b: &Value) -> bool {
+    match (a, b) {
+        (Value::Number(a), Value::Number(b)) => a == b,
+        (Value::Array(_) | Value::Object(_), _) => false,
+        _ => a == b,
+    }
+}
+
+/// JS `==`: operands of different types are compared as numbers, and
+/// arrays and objects as their string form.
+fn loose_equals(a: &Value, b: &Value) -> bool {
+    match (a, b) {
+        (Value::Null, other) | (other, Value::Null) => *other == Value::Null,
+        (Value::Bool(_), _) | (_, Value::Bool(_)) => loose_equals(&Value::Number(to_number(a)), &Value::Number(to_number(b))),
+        (Value::Number(n), Value::String(_)) => *n == to_number(b),
+        (Value::String(_), Value::Number(n)) => to_number(a) == *n,
+        (Value::Array(_) | Value::Object(_), Value::Number(_) | Value::String(_)) => loose_equals(&Value::String(to_js_string(a)), b),
+        (Value::Number(_) | Value::String(_), Value::Array(_) | Value::Object(_)) => loose_equals(a, &Value::String(to_js_string(b))),
+        _ => strict_equals(a, b),
+    }
+}
+
+fn to_number(value: &Value) -> f64 {
+    match value {
+        Value::Null => 0.0,
+        Value::Bool(b) => f64::from(u8::from(*b)),
+        Value::Number(n) => *n,
+        Value::String(s) if s.trim().is_empty() => 0.0,
+        Value::String(s) => s.trim().parse().unwrap_or(f64::NAN),
+        _ => f64::NAN,
+    }
+}
+
+/// JS `String(value)`.
+pub(crate) fn to_js_string(value: &Value) -> String {
+    match value {
+        Value::Null => "null".to_string(),
+        Value::Array(items) => items
+            .iter()
+            .map(|item| if *item == Value::Null { String::new() } else { to_js_string(item) })
+            .collect::<Vec<_>>()
+            .join(","),
+        Value::Object(_) => "[object Object]".to_string(),
+        other => other.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn eval(src: &str) -> Option<String> {
+        let scope = parse_literal("{ version: '2.1', user: { name: 'Ada' }, items: [1, 2, 3], zero: 0 }").unwrap();
+        evaluate(src, &scope).map(|v| to_js_string(&v))
+    }
+
+    #[test]
+    fn test_literals_and_operators() {
+        assert_eq!(eval(r#""`""#).as_deref(), Some("`"));
+        assert_eq!(eval("'{'").as_deref(), Some("{"));
+        assert_eq!(eval("2 * 60").as_deref(), Some("120"));
+        assert_eq!(eval("1 + 2 * 3 - 4 / 2").as_deref(), Some("5"));
+        assert_eq!(eval("(1 + 2) * 3 % 4").as_deref(), Some("1"));
+        assert_eq!(eval("'v' + 1 + 2").as_deref(), Some("v12"));
+        assert_eq!(eval("-zero === 0 && !false").as_deref(), Some("true"));
+        assert_eq!(eval("[1, 'a'].length >= 2").as_deref(), Some("true"));
+    }
+
+    #[test]
+    fn test_js_numbers_and_loose_equality() {
+        assert_eq!(eval("1/0").as_deref(), Some("Infinity"));
+        assert_eq!(eval("-1/0").as_deref(), Some("-Infinity"));
+        assert_eq!(eval("0/0").as_deref(), Some("NaN"));
+        assert_eq!(eval("-0").as_deref(), Some("0"));
+        assert_eq!(eval("1e21 + 1").as_deref(), Some("1e+21"));
+        assert_eq!(eval("0.0000001").as_deref(), Some("1e-7"));
+        assert_eq!(eval("0.5").as_deref(), Some("0.5"));
+        assert_eq!(eval("1e-3 + 2e+3 + 1E2").as_deref(), Some("2100.001"));
+        assert_eq!(eval("0x1e-3").as_deref(), Some("27"));
+        assert_eq!(eval("1 == '1' && 0 == false && null == null && [1] == 1").as_deref(), Some("true"));
+        assert_eq!(eval("1 != '1' || 1 === '1' || null == 0 || '' != 0").as_deref(), Some("false"));
+    }
+
+    #[test]
+    fn test_short_circuit() {
+        assert_eq!(eval("version || missing").as_deref(), Some("2.1"));
+        assert_eq!(eval("zero && missing.x").as_deref(), Some("0"));
+        assert_eq!(eval("version ?? missing").as_deref(), Some("2.1"));
+        assert_eq!(eval("zero ? missing : user.name").as_deref(), Some("Ada"));
+        assert_eq!(eval("version ? 'yes' : missing[0]").as_deref(), Some("yes"));
+        assert_eq!(eval("zero || missing"), None);
+        assert_eq!(eval("version || (1"), None);
+    }
+
+    #[test]
+    fn test_variables_templates_ternaries() {
+        assert_eq!(eval("`v${version}`").as_deref(), Some("v2.1"));
+        assert_eq!(eval("`${user.name} has ${items.length} items`").as_deref(), Some("Ada has 3 items"));
+        assert_eq!(eval("zero ? 'a' : items[1] > 1 ? 'b' : 'c'").as_deref(), Some("b"));
+        assert_eq!(eval("user?.missing?.name ?? 'none'").as_deref(), Some("none"));
+        assert_eq!(eval("user['name'] || 'x'").as_deref(), Some("Ada"));
+        let scope = Value::Object(Vec::new());
+        assert_eq!(evaluate("1 > 2 && 'x'", &scope).map(|v| to_jsx_text(&v)).as_deref(), Some(""));
+    }
+
+    #[test]
+    fn test_identifier_paths() {
+        assert_eq!(
+            identifier_paths("process.env.KEY + 'a.b' + `${user?.name}` + items[0].x"),
+            ["process.env.KEY", "user.name", "items.0.x"]
+        );
+        assert_eq!(
+            identifier_paths(r#"process["env"].KEY + process?.['env'] + a[b]"#),
+            ["process.env.KEY", "process.env", "a.*", "b"]
+        );
+        assert_eq!(
+            identifier_paths("(process).env.X + process .env\n.KEY + process /* c */ ?. env + process[ `env` ]"),
+            ["process.env.X", "process.env.KEY", "process.env", "process.env"]
+        );
+    }
+
+    #[test]
+    fn test_rejects_unsafe_or_unknown() {
+        assert_eq!(eval("alert(1)"), None);
+        assert_eq!(eval("user.name.toUpperCase()"), None);
+        assert_eq!(eval("x = 1"), None);
+        assert_eq!(eval("unknown + 1"), None);
+        assert_eq!(eval(&"(".repeat(200)), None);
+        assert_eq!(eval(&"!".repeat(100_000)), None);
+    }
+}
@@ -0,0 +1,281 @@
+use crate::ast::{JsxElement, MdxNode};
+use crate::config::Escape;
+use crate::escape::decode_entities;
+use crate::resolver::ResolveContext;
+use crate::tokenizer::{tokenize_with_raw_tags, RawAttrValue, Token};
+
+/// Built-in conversion of standard HTML elements to Markdown, used when
+/// `options.html_to_markdown` is on and no component rule matches the tag.
+/// Returns `None` for tags it doesn't know.
+pub(crate) fn render(element: &JsxElement<'_>, cx: &ResolveContext<'_>) -> Option<String> {
+    let tag = element.tag;
+    let out = match tag {
+        "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => {
+            let level = usize::from(tag.as_bytes()[1] - b'0');
+            block(&format!("{} {}", "#".repeat(level), inline(&cx.render_children())))
+        }
+        "p" => block(&cx.render_children()),
+        "strong" | "b" => wrap_inline(&cx.render_children(), "**"),
+        "em" | "i" => wrap_inline(&cx.render_children(), "*"),
+        "del" | "s" | "strike" => wrap_inline(&cx.render_children(), "~~"),
+        "code" => Escape::Code.apply(&decode_entities(&raw_text(element.children))),
+        "a" => {
+            let text = inline(&cx.render_children());
+            match attr(element, "href") {
+                Some(href) => format!("[{text}]({}{})", Escape::Url.apply(&href), title(element)),
+                None => text,
+            }
+        }
+        "img" => {
+            let src = attr(element, "src")?;
+            let alt = attr(element, "alt").unwrap_or_default();
+            format!("![{}]({}{})", Escape::LinkText.apply(&alt), Escape::Url.apply(&src), title(element))
+        }
+        "br" => "\\\n".to_string(),
+        "hr" => block("---"),
+        "pre" => block(&code_block(element)),
+        "blockquote" => block(&quote(&cx.render_children())),
+        "ul" | "ol" => block(&list(element, cx)),
+        "table" => block(&table(element, cx)),
+        "details" => block(&details(element, cx)),
+        "summary" => wrap_inline(&inline(&cx.render_children()), "**"),
+        "div" | "span" | "section" | "article" | "main" | "header" | "footer" | "figure" | "figcaption"
+        | "thead" | "tbody" | "tfoot" | "tr" | "td" | "th" | "li" | "u" | "small" | "mark" | "sup" | "sub" => {
+            cx.render_children()
+        }
+        _ => return None,
+    };
+    Some(out)
+}
+
+/// Surround a block with blank lines; extra ones are collapsed later.
+fn block(content: &str) -> String {
+    format!("\n\n{}\n\n", content.trim())
+}
+
+fn wrap_inline(content: &str, marker: &str) -> String {
+    let content = content.trim();
+    if content.is_empty() {
+        String::new()
+    } else {
+        format!("{marker}{content}{marker}")
+    }
+}
+
+fn inline(content: &str) -> String {
+    content.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+fn attr(element: &JsxElement<'_>, name: &str) -> Option<String> {
+    element.prop(name).map(|v| v.to_string())
+}
+
+fn title(element: &JsxElement<'_>) -> String {
+    match attr(element, "title") {
+        Some(title) => format!(" \"{}\"", title.replace('"', "\\\"")),
+        None => String::new(),
+    }
+}
+
+/// `<pre><code class="language-x">` becomes a fenced code block. The code is
+/// taken from the source text, not rendered as Markdown.
+fn code_block(element: &JsxElement<'_>) -> String {
+    let code_element = element.child_elements().find(|c| c.tag == "code");
+    let (code, class) = match &code_element {
+        Some(code) => (raw_text(code.children), attr(code, "className").or_else(|| attr(code, "class"))),
+        None => {
+            let text = raw_text(element.children);
+            inner_code(&text).unwrap_or((text, None))
+        }
+    };
+    let language = class
+        .as_deref()
+        .and_then(|c| c.split_whitespace().find_map(|c| c.strip_prefix("language-")))
+        .unwrap_or_default()
+        .to_string();
+    let code = decode_entities(&code);
+    let code = code.trim_matches('\n');
+    let longest = code.split(|c| c != '`').map(str::len).max().unwrap_or(0);
+    let fence = "`".repeat(longest.max(2) + 1);
+    format!("{fence}{language}\n{code}\n{fence}")
+}
+
+/// `<pre>` is a raw-text tag, so a `<code>` inside it arrives as text.
+/// Split it into the code and its class.
+fn inner_code(text: &str) -> Option<(String, Option<String>)> {
+    let tokens = tokenize_with_raw_tags(text.trim(), &["code"]).ok()?;
+    let [Token::JsxOpenTag { tag, attributes, .. }, Token::Markdown(code), Token::JsxCloseTag { .. }] = tokens.as_slice() else {
+        return None;
+    };
+    if tag != "code" {
+        return None;
+    }
+    let class = attributes.iter().find(|a| a.name == "className" || a.name == "class").and_then(|a| match &a.value {
+        Some(RawAttrValue::String(s)) => Some(s.clone()),
+        _ => None,
+    });
+    Some((code.clone(), class))
+}
+
+fn raw_text(nodes: &[MdxNode]) -> String {
+    let mut out = String::new();
+    for node in nodes {
+        match node {
+            MdxNode::Markdown(text) => out.push_str(text),
+            MdxNode::Expression(expr) => {
+                out.push('{');
+                out.push_str(expr);
+                out.push('}');
+            }
+            MdxNode::JsxElement { children, .. } => out.push_str(&raw_text(children)),
+            _ => {}
+        }
+    }
+    out
+}
+
+fn quote(content: &str) -> String {
+    content
+        .trim()
+        .lines()
+        .map(|line| if line.is_empty() { ">".to_string() } else { format!("> {line}") })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// `<ul>`/`<ol>` items, numbered from `start` for ordered lists. Item content
+/// is indented under its marker so nested blocks stay in the item.
+fn list(element: &JsxElement<'_>, cx: &ResolveContext<'_>) -> String {
+    let ordered = element.tag == "ol";
+    let start = element
+        .prop("start")
+        .and_then(|v| v.to_string().parse::<usize>().ok())
+        .unwrap_or(1);
+    let mut items = Vec::new();
+    let items_at = element.indexed_child_elements().filter(|(_, c)| c.tag == "li");
+    for (i, (index, _)) in items_at.enumerate() {
+        let marker = if ordered { format!("{}. ", start + i) } else { "- ".to_string() };
+        let pad = " ".repeat(marker.len());
+        let content = cx.render_descendant(&[index]);
+        let mut lines = content.trim().lines();
+        let mut rendered = format!("{marker}{}", lines.next().unwrap_or_default());
+        for line in lines {
+            rendered.push('\n');
+            if !line.is_empty() {
+                rendered.push_str(&pad);
+                rendered.push_str(line);
+            }
+        }
+        items.push(rendered);
+    }
+    items.join("\n")
+}
+
+/// A GFM table. The first row is the header, whether it uses `<th>` or not.
+fn table(element: &JsxElement<'_>, cx: &ResolveContext<'_>) -> String {
+    let mut rows: Vec<Vec<String>> = Vec::new();
+    collect_rows(element, &[], cx, &mut rows);
+    let Some(columns) = rows.iter().map(Vec::len).max().filter(|n| *n > 0) else {
+        return String::new();
+    };
+
+    let line = |cells: &[String]| {
+        let mut cells = cells.to_vec();
+        cells.resize(columns, String::new());
+        format!("| {} |", cells.join(" | "))
+    };
+    let mut out = vec![line(&rows[0]), format!("|{}", " --- |".repeat(columns))];
+    out.extend(rows[1..].iter().map(|row| line(row)));
+    out.join("\n")
+}
+
+/// `path` is where `element` sits below the table, so cells render with
+/// their real ancestors.
+fn collect_rows(element: &JsxElement<'_>, path: &[usize], cx: &ResolveContext<'_>, rows: &mut Vec<Vec<String>>) {
+    for (index, child) in element.indexed_child_elements() {
+        let path = [path, &[index]].concat();
+        match child.tag {
+            "thead" | "tbody" | "tfoot" => collect_rows(&child, &path, cx, rows),
+            "tr" => rows.push(
+                child
+                    .indexed_child_elements()
+                    .filter(|(_, c)| matches!(c.tag, "th" | "td"))
+                    .map(|(cell, _)| inline(&cx.render_descendant(&[path.as_slice(), &[cell]].concat())).replace('|', "\\|"))
+                    .collect(),
+            ),
+            _ => {}
+        }
+    }
+}
+
+/// `<details>` becomes its bold `<summary>` followed by the content.
+fn details(element: &JsxElement<'_>, cx: &ResolveContext<'_>) -> String {
+    let summary = element.indexed_child_elements().find(|(_, c)| c.tag == "summary");
+    let body: Vec<MdxNode> = element
+        .children
+        .iter()
+        .filter(|n| !matches!(n.as_element(), Some(e) if e.tag == "summary"))
+        .cloned()
+        .collect();
+    let content = cx.render_nodes(&body);
+    match summary {
+        Some((index, _)) => {
+            let title = inline(&cx.render_descendant(&[index]));
+            format!("**{title}**\n\n{content}")
+        }
+        None => content,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::Config;
+    use crate::convert;
+
+    fn html(input: &str) -> String {
+        let config = Config::from_toml("[options]\nhtml_to_markdown = true").unwrap();
+        convert(input, &config).unwrap()
+    }
+
+    #[test]
+    fn test_inline_and_headings() {
+        assert_eq!(
+            html("<h2>Title</h2>\n<p>A <strong>b</strong> <em>c</em> <a href=\"/x\">link</a> <code>a &lt; b</code></p>"),
+            "## Title\n\nA **b** *c* [link](/x) `a < b`\n"
+        );
+        assert_eq!(html("<img src=\"/a.png\" alt=\"An image\" />"), "![An image](/a.png)\n");
+    }
+
+    #[test]
+    fn test_lists() {
+        assert_eq!(
+            html("<ol start=\"3\"><li>one</li><li>two<ul><li>nested</li></ul></li></ol>"),
+            "3. one\n4. two\n\n   - nested\n"
+        );
+    }
+
+    #[test]
+    fn test_pre_table_details() {
+        assert_eq!(
+            html("<pre><code class=\"language-py\">if a &lt; b:\n    pass</code></pre>"),
+            "```py\nif a < b:\n    pass\n```\n"
+        );
+        assert_eq!(
+            html("<table><tr><th>A</th><th>B</th></tr><tr><td>1</td><td>x | y</td></tr></table>"),
+            "| A | B |\n| --- | --- |\n| 1 | x \\| y |\n"
+        );
+        assert_eq!(
+            html("<details><summary>More</summary>\n\nHidden text\n\n</details>"),
+            "**More**\n\nHidden text\n"
+        );
+    }
+
+    #[test]
+    fn test_component_rules_override() {
+        let config = Config::from_toml(
+            "[options]\nhtml_to_markdown = true\n[components.h1]\ntemplate = \"TITLE: {children}\"\n[components._default]\nmode = \"drop\"",
+        )
+        .unwrap();
+        assert_eq!(convert("<h1>Hi</h1> <em>x</em> <Custom />", &config).unwrap(), "TITLE: Hi *x*\n");
+    }
+}
@@ -0,0 +1,464 @@
+use crate::ast::{AttrValue, Attribute, MdxDocument, MdxNode};
+use crate::config::{Escape, HtmlInput};
+use crate::escape::decode_entities;
+
+/// Elements that never have children or a closing tag.
+const VOID: &[&str] = &[
+    "area", "base", "br", "col", "embed", "hr", "img", "input", "link", "meta", "source", "track", "wbr",
+];
+
+/// Elements whose content is raw text, not markup.
+const RAW_TEXT: &[&str] = &["script", "style", "textarea", "title"];
+
+/// Block-level elements: whitespace around their tags is not significant, and
+/// opening one closes an open `<p>`.
+const BLOCK: &[&str] = &[
+    "address", "article", "aside", "blockquote", "body", "dd", "details", "div", "dl", "dt", "fieldset",
+    "figcaption", "figure", "footer", "form", "h1", "h2", "h3", "h4", "h5", "h6", "head", "header", "hr",
+    "html", "li", "main", "nav", "ol", "p", "pre", "section", "summary", "table", "tbody", "td", "tfoot",
+    "th", "thead", "tr", "ul",
+];
+
+/// Parse an HTML document into the same AST as MDX: elements become
+/// `JsxElement`s with lowercase tags and text becomes `Markdown` nodes with
+/// Markdown syntax escaped. The parser is lenient: unclosed and misnested
+/// tags are closed implicitly, and comments and doctypes are dropped.
+pub(crate) fn parse_html(input: &str) -> MdxDocument {
+    let mut parser = HtmlParser {
+        input,
+        pos: 0,
+        stack: vec![Open {
+            tag: String::new(),
+            attributes: Vec::new(),
+            children: Vec::new(),
+        }],
+    };
+    parser.run();
+    while parser.stack.len() > 1 {
+        parser.close_top();
+    }
+    MdxDocument {
+        nodes: parser.stack.pop().map(|root| root.children).unwrap_or_default(),
+        source: String::new(),
+    }
+}
+
+/// Keep the main content of a parsed page: drop the configured elements
+/// (`head`, `script`, `nav`, ...), then take the children of the element
+/// matching `selector`, or else of the first `<main>`, `<article>` or `<body>`.
+pub(crate) fn extract_content(doc: MdxDocument, options: &HtmlInput) -> MdxDocument {
+    let mut nodes = doc.nodes;
+    drop_elements(&mut nodes, &options.drop);
+
+    let selected = match &options.selector {
+        Some(selector) => find(&nodes, &|tag, attrs| selector_matches(selector, tag, attrs)),
+        None => ["main", "article", "body"]
+            .iter()
+            .find_map(|name| find(&nodes, &|tag, _| tag == *name)),
+    };
+    MdxDocument {
+        nodes: selected.unwrap_or(nodes),
+        source: String::new(),
+    }
+}
+
+struct Open {
+    tag: String,
+    attributes: Vec<Attribute>,
+    children: Vec<MdxNode>,
+}
+
+struct HtmlParser<'a> {
+    input: &'a str,
+    pos: usize,
+    stack: Vec<Open>,
+}
+
+impl<'a> HtmlParser<'a> {
+    fn rest(&self) -> &'a str {
+        &self.input[self.pos..]
+    }
+
+    fn run(&mut self) {
+        while self.pos < self.input.len() {
+            let rest = self.rest();
+            if rest.starts_with("<!--") {
+                self.pos += rest.find("-->").map_or(rest.len(), |i| i + 3);
+            } else if rest.starts_with("<!") || rest.starts_with("<?") {
+                self.pos += rest.find('>').map_or(rest.len(), |i| i + 1);
+            } else if rest.starts_with("</") {
+                self.close_tag();
+            } else if rest.starts_with('<') && rest[1..].starts_with(|c: char| c.is_ascii_alphabetic()) {
+                self.open_tag();
+            } else {
+                // Always consume the first character, which may be a stray `<`
+                let first = rest.chars().next().map_or(1, char::len_utf8);
+                let end = rest[first..].find('<').map_or(rest.len(), |i| i + first);
+                let text = &rest[..end];
+                self.pos += end;
+                self.push_text(text);
+            }
+        }
+    }
+
+    /// Inside `<pre>` or `<code>` text is kept as written; the HTML
+    /// conversion decodes it when building the code block or span.
+    fn in_code(&self) -> bool {
+        self.stack.iter().any(|open| open.tag == "pre" || open.tag == "code")
+    }
+
+    fn push_text(&mut self, raw: &str) {
+        if self.in_code() {
+            self.push_node(MdxNode::Markdown(raw.to_string()));
+            return;
+        }
+        let mut text = collapse_whitespace(&decode_entities(raw));
+        // Whitespace right after a block boundary is not significant
+        let after_block = match self.top().children.last() {
+            Some(MdxNode::JsxElement { tag, .. }) => is_block(tag),
+            Some(_) => false,
+            None => is_block(&self.top().tag) || self.stack.len() == 1,
+        };
+        if after_block {
+            text = text.trim_start().to_string();
+        }
+        if !text.is_empty() {
+            let escaped = Escape::Markdown.apply(&text);
+            self.push_node(MdxNode::Markdown(escaped));
+        }
+    }
+
+    fn open_tag(&mut self) {
+        let rest = self.rest();
+        let name_end = rest[1..]
+            .find(|c: char| !(c.is_ascii_alphanumeric() || c == '-' || c == ':'))
+            .map_or(rest.len(), |i| i + 1);
+        let tag = rest[1..name_end].to_ascii_lowercase();
+        self.pos += name_end;
+        let (attributes, self_closing) = self.attributes();
+
+        self.close_implied_by(&tag);
+        if is_block(&tag) {
+            self.trim_trailing_text();
+        }
+
+        if RAW_TEXT.contains(&tag.as_str()) {
+            let close = format!("</{tag}");
+            let rest = self.rest();
+            let end = find_ignore_case(rest, &close).unwrap_or(rest.len());
+            let text = rest[..end].to_string();
+            self.pos += end;
+            self.pos += self.rest().find('>').map_or(self.rest().len(), |i| i + 1);
+            self.push_node(MdxNode::JsxElement {
+                tag,
+                attributes,
+                children: vec![MdxNode::Markdown(text)],
+                self_closing: false,
+                span: None,
+            });
+        } else if self_closing || VOID.contains(&tag.as_str()) {
+            self.push_node(MdxNode::JsxElement {
+                tag,
+                attributes,
+                children: Vec::new(),
+                self_closing: true,
+                span: None,
+            });
+        } else {
+            self.stack.push(Open {
+                tag,
+                attributes,
+                children: Vec::new(),
+            });
+        }
+    }
+
+    /// Parse attributes up to and including `>`. Returns whether the tag
+    /// ended with `/>`.
+    fn attributes(&mut self) -> (Vec<Attribute>, bool) {
+        let mut attributes = Vec::new();
+        loop {
+            let rest = self.rest();
+            let trimmed = rest.trim_start();
+            self.pos += rest.len() - trimmed.len();
+            if trimmed.is_empty() {
+                return (attributes, false);
+            }
+            if let Some(after) = trimmed.strip_prefix("/>") {
+                self.pos = self.input.len() - after.len();
+                return (attributes, true);
+            }
+            if trimmed.starts_with('>') {
+                self.pos += 1;
+                return (attributes, false);
+            }
+            let name_end = trimmed
+                .find(|c: char| c.is_whitespace() || matches!(c, '=' | '>' | '/'))
+                .unwrap_or(trimmed.len())
+                .max(1);
+            let name = trimmed[..name_end].to_ascii_lowercase();
+            self.pos += name_end;
+
+            let rest = self.rest();
+            let after_ws = rest.trim_start();
+            let value = if let Some(after_eq) = after_ws.strip_prefix('=') {
+                let after_eq = after_eq.trim_start();
+                let (value, consumed) = match after_eq.chars().next() {
+                    Some(q @ ('"' | '\'')) => {
+                        let end = after_eq[1..].find(q).map_or(after_eq.len(), |i| i + 1);
+                        (&after_eq[1..end], (end + 1).min(after_eq.len()))
+                    }
+                    _ => {
+                        let end = after_eq
+                            .find(|c: char| c.is_whitespace() || c == '>')
+                            .unwrap_or(after_eq.len());
+                        (&after_eq[..end], end)
+                    }
+                };
+                self.pos = self.input.len() - after_eq.len() + consumed;
+                Some(AttrValue::String(decode_entities(value)))
+            } else {
+                None
+            };
+            // `class` is the HTML spelling of `className`
+            let name = if name == "class" { "className".to_string() } else { name };
+            attributes.push(Attribute { name, value });
+        }
+    }
+
+    fn close_tag(&mut self) {
+        let rest = self.rest();
+        let end = rest.find('>').map_or(rest.len(), |i| i + 1);
+        let tag = rest[2..end].trim_end_matches('>').trim().to_ascii_lowercase();
+        self.pos += end;
+        // Ignore stray closing tags
+        if let Some(depth) = self.stack.iter().rposition(|open| open.tag == tag) {
+            if depth > 0 {
+                while self.stack.len() > depth {
+                    self.close_top();
+                }
+            }
+        }
+    }
+
+    /// Close elements that the new tag ends implicitly (`<li>` after `<li>`,
+    /// a block inside `<p>`).
+    fn close_implied_by(&mut self, tag: &str) {
+        let closes: &[&str] = match tag {
+            "li" => &["li"],
+            "dt" | "dd" => &["dt", "dd"],
+            "tr" => &["tr", "td", "th"],
+            "td" | "th" => &["td", "th"],
+            "option" => &["option"],
+            _ if is_block(tag) => &["p"],
+            _ => &[],
+        };
+        // Only close within the nearest list/table container, or for `<p>`
+        // within the nearest enclosing block
+        let boundary = ["ul", "ol", "dl", "table", "tbody", "thead", "tfoot", "select"];
+        let mut target = None;
+        for i in (1..self.stack.len()).rev() {
+            let open = self.stack[i].tag.as_str();
+            if closes.contains(&open) {
+                target = Some(i);
+            } else if boundary.contains(&open) || (closes == ["p"] && is_block(open)) {
+                break;
+            }
+        }
+        if let Some(i) = target {
+            while self.stack.len() > i {
+                self.close_top();
+            }
+        }
+    }
+
+    fn close_top(&mut self) {
+        self.trim_trailing_text();
+        let open = self.stack.pop().expect("root is never closed");
+        self.push_node(MdxNode::JsxElement {
+            tag: open.tag,
+            attributes: open.attributes,
+            children: open.children,
+            self_closing: false,
+            span: None,
+        });
+    }
+
+    /// Whitespace before a block boundary is not significant.
+    fn trim_trailing_text(&mut self) {
+        if self.in_code() {
+            return;
+        }
+        let children = &mut self.top_mut().children;
+        if let Some(MdxNode::Markdown(text)) = children.last_mut() {
+            let trimmed = text.trim_end().len();
+            text.truncate(trimmed);
+            if text.is_empty() {
+                children.pop();
+            }
+        }
+    }
+
+    fn top(&self) -> &Open {
+        self.stack.last().expect("root is always open")
+    }
+
+    fn top_mut(&mut self) -> &mut Open {
+        self.stack.last_mut().expect("root is always open")
+    }
+
+    fn push_node(&mut self, node: MdxNode) {
+        self.top_mut().children.push(node);
+    }
+}
+
+fn is_block(tag: &str) -> bool {
+    BLOCK.contains(&tag)
+}
+
+fn collapse_whitespace(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut in_space = false;
+    for c in text.chars() {
+        if c.is_whitespace() && c != '\u{a0}' {
+            if !in_space {
+                out.push(' ');
+            }
+            in_space = true;
+        } else {
+            out.push(c);
+            in_space = false;
+        }
+    }
+    out
+}
+
+fn find_ignore_case(haystack: &str, needle: &str) -> Option<usize> {
+    haystack
+        .as_bytes()
+        .windows(needle.len())
+        .position(|w| w.eq_ignore_ascii_case(needle.as_bytes()))
+}
+
+fn drop_elements(nodes: &mut Vec<MdxNode>, drop: &[String]) {
+    nodes.retain(|node| !matches!(node, MdxNode::JsxElement { tag, .. } if drop.contains(tag)));
+    for node in nodes {
+        if let MdxNode::JsxElement { children, .. } = node {
+            drop_elements(children, drop);
+        }
+    }
+}
+
+/// Depth-first search for the first element matching `pred`; returns its
+/// children.
+fn find(nodes: &[MdxNode], pred: &dyn Fn(&str, &[Attribute]) -> bool) -> Option<Vec<MdxNode>> {
+    nodes.iter().find_map(|node| match node {
+        MdxNode::JsxElement {
+            tag,
+            attributes,
+            children,
+            ..
+        } => {
+            if pred(tag, attributes) {
+                Some(children.clone())
+            } else {
+                find(children, pred)
+            }
+        }
+        _ => None,
+    })
+}
+
+/// Simple selectors: `tag`, `#id`, `.class`, and combinations like
+/// `div.content` or `section#docs.main`.
+fn selector_matches(selector: &str, tag: &str, attributes: &[Attribute]) -> bool {
+    let attr = |name: &str| {
+        attributes.iter().find(|a| a.name == name).and_then(|a| match &a.value {
+            Some(AttrValue::String(s)) => Some(s.as_str()),
+            _ => None,
+        })
+    };
+    let mut parts = selector.trim().split_inclusive(['.', '#']).peekable();
+    let mut segments: Vec<(char, String)> = Vec::new();
+    let mut kind = ' ';
+    for part in parts.by_ref() {
+        let (text, next_kind) = match part.chars().last() {
+            Some(c @ ('.' | '#')) => (&part[..part.len() - 1], c),
+            _ => (part, ' '),
+        };
+        if !text.is_empty() {
+            segments.push((kind, text.to_string()));
+        }
+        kind = next_kind;
+    }
+    segments.iter().all(|(kind, name)| match kind {
+        '.' => attr("className").is_some_and(|c| c.split_whitespace().any(|c| c == name)),
+        '#' => attr("id") == Some(name.as_str()),
+        _ => tag.eq_ignore_ascii_case(name),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use crate::convert;
+
+    const PAGE: &str = r#"<!DOCTYPE html>
+<html>
+<head>
+  <title>Page</title>
+  <script>window.x = { a: 1 };</script>
+  <style>body { color: red }</style>
+</head>
+<body>
+  <nav><a href="/">Home</a></nav>
+  <main>
+    <h1>Hello *world*</h1>
+    <p>First<br>line
+       continues.
+    <p>Second &amp; <b>bold</b> <code>a &lt; b</code>.
+    <table><tr><th>A<th>B<tr><td>1<td>2</table>
+    <ul>
+      <li>one
+      <li>two
+    </ul>
+  </main>
+  <footer>Copyright</footer>
+</body>
+</html>"#;
+
+    fn html_config(extra: &str) -> Config {
+        Config::from_toml(&format!("[options]\ninput_format = \"html\"\n{extra}")).unwrap()
+    }
+
+    #[test]
+    fn test_html_document_main_content() {
+        let result = convert(PAGE, &html_config("")).unwrap();
+        assert_eq!(
+            result,
+            "# Hello \\*world\\*\n\nFirst\\\nline continues.\n\nSecond & **bold** `a < b`.\n\n| A | B |\n| --- | --- |\n| 1 | 2 |\n\n- one\n- two\n"
+        );
+    }
+
+    #[test]
+    fn test_selector() {
+        let input = "<div id=\"app\"><div class=\"sidebar\">Menu</div><div class=\"content main\"><p>Body</p></div></div>";
+        let config = html_config("[html]\nselector = \"div.content\"");
+        assert_eq!(convert(input, &config).unwrap(), "Body\n");
+    }
+
+    #[test]
+    fn test_non_ascii_text() {
+        let config = html_config("");
+        assert_eq!(convert("<p>élan vital</p>", &config).unwrap(), "élan vital\n");
+        assert_eq!(convert("<p>a <b>b</b>—c</p>", &config).unwrap(), "a **b**—c\n");
+    }
+
+    #[test]
+    fn test_lenient_parsing() {
+        let doc = parse_html("<P CLASS=x>a<b>b</p>c<br>");
+        assert_eq!(doc.nodes.len(), 3);
+        assert!(matches!(&doc.nodes[0], MdxNode::JsxElement { tag, children, .. } if tag == "p" && children.len() == 2));
+    }
+}
@@ -1,33 +1,108 @@
 pub mod ast;
 pub mod config;
+pub mod diagnostic;
+pub mod matcher;
 pub mod parser;
+pub mod preset;
+pub mod resolver;
 pub mod rewriter;
+#[cfg(feature = "scripting")]
+pub mod script;
+mod escape;
+mod eval;
+mod html;
+mod html_document;
+mod scope;
+mod serialize;
+mod slug;
+mod targets;
+mod template;
 pub mod tokenizer;
 pub mod transform;
+pub mod value;
+mod whitespace;
 
-use config::Config;
-pub use transform::ComponentResolver;
+use ast::MdxDocument;
+use config::{Config, InputFormat};
+use std::borrow::Cow;
+pub use ast::JsxElement;
+pub use diagnostic::Diagnostic;
+pub use resolver::{ElementResolver, LegacyResolver, ResolveContext, ResolveError};
+pub use transform::{ComponentResolver, RenderContext};
 
 /// Full MDX-to-Markdown conversion pipeline (Layer 1 + Layer 2).
+///
+/// Diagnostics are dropped, including errors such as a missing required
+/// prop; use [`convert_with_diagnostics`] to get them.
 pub fn convert(mdx: &str, config: &Config) -> Result<String, ConvertError> {
-    let tokens = tokenizer::tokenize(mdx).map_err(|e| ConvertError(e.message))?;
-    let doc = parser::parse(tokens).map_err(|e| ConvertError(e.message))?;
-    let raw_md = transform::transform(&doc, config);
-    let final_md = rewriter::rewrite_markdown(&raw_md, config);
-    Ok(final_md)
+    Ok(convert_with_diagnostics(mdx, config)?.markdown)
+}
+
+/// Full pipeline, returning diagnostics alongside the Markdown.
+pub fn convert_with_diagnostics(mdx: &str, config: &Config) -> Result<Conversion, ConvertError> {
+    convert_with_element_resolver(mdx, config, &resolver::NoResolver)
 }
 
 /// Full pipeline with an external component resolver (for WASM JS callbacks).
+/// Diagnostics are dropped, as with [`convert`]; see
+/// [`convert_with_element_resolver`] and [`LegacyResolver`] to get them.
 pub fn convert_with_resolver(
     mdx: &str,
     config: &Config,
     resolver: &dyn ComponentResolver,
 ) -> Result<String, ConvertError> {
-    let tokens = tokenizer::tokenize(mdx).map_err(|e| ConvertError(e.message))?;
-    let doc = parser::parse(tokens).map_err(|e| ConvertError(e.message))?;
-    let raw_md = transform::transform_with_resolver(&doc, config, resolver);
-    let final_md = rewriter::rewrite_markdown(&raw_md, config);
-    Ok(final_md)
+    Ok(convert_with_element_resolver(mdx, config, &LegacyResolver(resolver))?.markdown)
+}
+
+/// Parse the input according to `options.input_format`. HTML documents are
+/// reduced to their main content and always use the built-in HTML conversion.
+fn parse_input<'c>(input: &str, config: &'c Config) -> Result<(MdxDocument, Cow<'c, Config>), ConvertError> {
+    match config.options.input_format {
+        InputFormat::Mdx => {
+            let tokens = tokenizer::tokenize_spanned(input, &config.options.raw_text_tags).map_err(|e| ConvertError(e.message))?;
+            let doc = parser::parse_spanned(tokens, input).map_err(|e| ConvertError(e.message))?;
+            Ok((doc, Cow::Borrowed(config)))
+        }
+        InputFormat::Html => {
+            let doc = html_document::extract_content(html_document::parse_html(input), &config.html);
+            let mut config = config.clone();
+            config.options.html_to_markdown = true;
+            Ok((doc, Cow::Owned(config)))
+        }
+    }
+}
+
+/// Full pipeline with an [`ElementResolver`], returning diagnostics alongside
+/// the Markdown. Configs that can't be applied, such as ones with invalid
+/// component or filter patterns, are rejected.
+pub fn convert_with_element_resolver(
+    mdx: &str,
+    config: &Config,
+    resolver: &dyn ElementResolver,
+) -> Result<Conversion, ConvertError> {
+    let (doc, config) = parse_input(mdx, config)?;
+    let config = config.as_ref();
+    let rules = transform::Rules::new(config).map_err(|e| ConvertError(e.message))?;
+    let (raw_md, diagnostics) = transform::render_document(&doc, config, &rules, resolver);
+    let markdown = rewriter::rewrite_markdown(&raw_md, config);
+    Ok(Conversion { markdown, diagnostics })
+}
+
+/// Output of a conversion that also reports diagnostics.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Conversion {
+    pub markdown: String,
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+impl Conversion {
+    /// Whether any diagnostic is an error. The Markdown is still complete,
+    /// but some elements were rendered differently than configured.
+    pub fn has_errors(&self) -> bool {
+        self.diagnostics
+            .iter()
+            .any(|d| d.severity == diagnostic::Severity::Error)
+    }
 }
 
 #[derive(Debug)]
@@ -160,4 +235,14 @@ template = "{children}"
     fn normalize(s: &str) -> Vec<String> {
         s.lines().map(|l| l.trim_end().to_string()).collect()
     }
+
+    #[test]
+    fn test_convert_with_diagnostics() {
+        let config = Config::from_toml("[components.Link]\ntemplate = \"[{children}]({href})\"\nrequired = [\"href\"]").unwrap();
+        let result = convert_with_diagnostics("<Link>docs</Link>\n", &config).unwrap();
+        assert_eq!(result.markdown, "docs\n");
+        assert!(result.has_errors());
+        assert_eq!(convert("<Link>docs</Link>\n", &config).unwrap(), result.markdown);
+        assert!(!convert_with_diagnostics("<Link href=\"/x\">docs</Link>\n", &config).unwrap().has_errors());
+    }
 }
@@ -0,0 +1,236 @@
+use crate::config::ComponentTransform;
+use regex::Regex;
+use std::collections::HashMap;
+
+/// Resolves a JSX tag name to a component rule.
+///
+/// Patterns can be:
+/// - an exact tag name: `Callout`
+/// - a glob: `Icon*`, `Docs.*` (`*` and `?` don't cross `.`; `**` does)
+/// - a regex wrapped in slashes: `/^UI\.[A-Z]/`
+/// - `_default`, the catch-all
+///
+/// Precedence: exact > glob (the pattern with more literal characters wins) >
+/// regex (in pattern order) > `_default`.
+#[derive(Debug, Clone)]
+pub struct ComponentMatcher<T> {
+    exact: HashMap<String, T>,
+    globs: Vec<(String, T)>,
+    regexes: Vec<(Regex, T)>,
+    default: Option<T>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PatternError {
+    pub message: String,
+}
+
+impl std::fmt::Display for PatternError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Pattern error: {}", self.message)
+    }
+}
+
+impl std::error::Error for PatternError {}
+
+impl<T> Default for ComponentMatcher<T> {
+    fn default() -> Self {
+        Self {
+            exact: HashMap::new(),
+            globs: Vec::new(),
+            regexes: Vec::new(),
+            default: None,
+        }
+    }
+}
+
+impl<T> ComponentMatcher<T> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a pattern. When the same exact name or pattern is added twice,
+    /// the first one is kept.
+    pub fn insert(&mut self, pattern: &str, value: T) -> Result<(), PatternError> {
+        if pattern == "_default" {
+            if self.default.is_none() {
+                self.default = Some(value);
+            }
+        } else if let Some(re) = regex_source(pattern) {
+            let regex = Regex::new(re).map_err(|e| PatternError {
+                message: format!("invalid component pattern {pattern}: {e}"),
+            })?;
+            if !self.regexes.iter().any(|(r, _)| r.as_str() == regex.as_str()) {
+                self.regexes.push((regex, value));
+            }
+        } else if pattern.contains(['*', '?']) {
+            if !self.globs.iter().any(|(g, _)| g == pattern) {
+                self.globs.push((pattern.to_string(), value));
+                // Most specific first; ties broken alphabetically for determinism
+                self.globs
+                    .sort_by(|(a, _), (b, _)| literal_len(b).cmp(&literal_len(a)).then_with(|| a.cmp(b)));
+            }
+        } else {
+            self.exact.entry(pattern.to_string()).or_insert(value);
+        }
+        Ok(())
+    }
+
+    /// Find the rule for a tag, falling back to `_default`.
+    pub fn find(&self, tag: &str) -> Option<&T> {
+        self.find_pattern(tag).or(self.default.as_ref())
+    }
+
+    /// Find the rule for a tag without the `_default` fallback.
+    pub fn find_pattern(&self, tag: &str) -> Option<&T> {
+        if let Some(v) = self.exact.get(tag) {
+            return Some(v);
+        }
+        if let Some((_, v)) = self.globs.iter().find(|(g, _)| glob_matches(g, tag)) {
+            return Some(v);
+        }
+        self.regexes.iter().find(|(r, _)| r.is_match(tag)).map(|(_, v)| v)
+    }
+}
+
+impl<'a> ComponentMatcher<&'a ComponentTransform> {
+    /// Build a matcher from `[components]`: each key is a pattern, and each
+    /// rule's `match` list adds more patterns for the same rule. Rules merged
+    /// from presets are left out; see [`from_presets`](Self::from_presets).
+    pub fn from_components(components: &'a HashMap<String, ComponentTransform>) -> Result<Self, PatternError> {
+        Self::from_rules(components, false)
+    }
+
+    /// Build a matcher from the rules merged from presets, to be consulted
+    /// when the [`from_components`](Self::from_components) one finds nothing.
+    pub fn from_presets(components: &'a HashMap<String, ComponentTransform>) -> Result<Self, PatternError> {
+        Self::from_rules(components, true)
+    }
+
+    fn from_rules(components: &'a HashMap<String, ComponentTransform>, presets: bool) -> Result<Self, PatternError> {
+        let mut keys: Vec<&String> = components
+            .iter()
+            .filter(|(_, rule)| rule.from_preset == presets)
+            .map(|(key, _)| key)
+            .collect();
+        keys.sort();
+
+        let mut matcher = Self::new();
+        for key in &keys {
+            matcher.insert(key, &components[*key])?;
+        }
+        for key in &keys {
+            let rule = &components[*key];
+            for pattern in &rule.matches {
+                matcher.insert(pattern, rule)?;
+            }
+        }
+        Ok(matcher)
+    }
+}
+
+fn regex_source(pattern: &str) -> Option<&str> {
+    pattern
+        .strip_prefix('/')
+        .and_then(|p| p.strip_suffix('/'))
+        .filter(|p| !p.is_empty())
+}
+
+fn literal_len(glob: &str) -> usize {
+    glob.chars().filter(|c| !matches!(c, '*' | '?')).count()
+}
+
+/// Match a tag against a glob. `*` matches any run of characters within a
+/// `.`-separated segment, `**` matches across segments, `?` matches one
+/// non-`.` character.
+fn glob_matches(glob: &str, tag: &str) -> bool {
+    let glob: Vec<char> = glob.chars().collect();
+    let tag: Vec<char> = tag.chars().collect();
+    glob_matches_at(&glob, &tag)
+}
+
+fn glob_matches_at(glob: &[char], tag: &[char]) -> bool {
+    match glob.first() {
+        None => tag.is_empty(),
+        Some('*') => {
+            let crosses_dots = glob.get(1) == Some(&'*');
+            let rest = if crosses_dots { &glob[2..] } else { &glob[1..] };
+            for i in 0..=tag.len() {
+                if glob_matches_at(rest, &tag[i..]) {
+                    return true;
+                }
+                if i < tag.len() && tag[i] == '.' && !crosses_dots {
+                    return false;
+                }
+            }
+            false
+        }
+        Some('?') => !tag.is_empty() && tag[0] != '.' && glob_matches_at(&glob[1..], &tag[1..]),
+        Some(c) => tag.first() == Some(c) && glob_matches_at(&glob[1..], &tag[1..]),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn matcher(patterns: &[&'static str]) -> ComponentMatcher<&'static str> {
+        let mut m = ComponentMatcher::new();
+        for p in patterns {
+            m.insert(p, *p).unwrap();
+        }
+        m
+    }
+
+    #[test]
+    fn test_glob_matching() {
+        assert!(glob_matches("Icon*", "IconArrow"));
+        assert!(glob_matches("Icon*", "Icon"));
+        assert!(!glob_matches("Icon*", "MyIcon"));
+        assert!(glob_matches("Docs.*", "Docs.Card"));
+        assert!(!glob_matches("Docs.*", "Docs.Card.Header"));
+        assert!(glob_matches("Docs.**", "Docs.Card.Header"));
+        assert!(glob_matches("?ab", "Tab"));
+        assert!(!glob_matches("*", "UI.Button"));
+    }
+
+    #[test]
+    fn test_precedence() {
+        let m = matcher(&["_default", "/^Icon/", "Icon*", "IconArrow*", "IconArrowLeft"]);
+        assert_eq!(m.find("IconArrowLeft"), Some(&"IconArrowLeft"));
+        assert_eq!(m.find("IconArrowRight"), Some(&"IconArrow*"));
+        assert_eq!(m.find("IconStar"), Some(&"Icon*"));
+        assert_eq!(m.find("Other"), Some(&"_default"));
+        assert_eq!(m.find_pattern("Other"), None);
+    }
+
+    #[test]
+    fn test_regex_and_namespaces() {
+        let m = matcher(&["/^UI\\.[A-Z]\\w*$/", "Docs.*"]);
+        assert_eq!(m.find("UI.Button"), Some(&"/^UI\\.[A-Z]\\w*$/"));
+        assert_eq!(m.find("UI.button"), None);
+        assert_eq!(m.find("Docs.Card"), Some(&"Docs.*"));
+    }
+
+    #[test]
+    fn test_invalid_regex() {
+        let mut m = ComponentMatcher::new();
+        assert!(m.insert("/(unclosed/", ()).is_err());
+    }
+
+    #[test]
+    fn test_from_components_match_list() {
+        let mut components = HashMap::new();
+        components.insert(
+            "admonition".to_string(),
+            ComponentTransform {
+                template: "> {children}".to_string(),
+                matches: vec!["Note".to_string(), "Tip".to_string()],
+                ..Default::default()
+            },
+        );
+        let m = ComponentMatcher::from_components(&components).unwrap();
+        assert_eq!(m.find("Tip").unwrap().template, "> {children}");
+        assert!(m.find("Warning").is_none());
+    }
+}
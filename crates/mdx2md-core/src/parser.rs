@@ -1,5 +1,6 @@
 use crate::ast::*;
 use crate::tokenizer::*;
+use std::ops::Range;
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct ParseError {
@@ -15,19 +16,41 @@ impl std::fmt::Display for ParseError {
 impl std::error::Error for ParseError {}
 
 pub fn parse(tokens: Vec<Token>) -> Result<MdxDocument, ParseError> {
-    let mut parser = Parser::new(tokens);
+    let mut parser = Parser::new(tokens, Vec::new());
     let nodes = parser.parse_nodes(None)?;
-    Ok(MdxDocument { nodes })
+    Ok(MdxDocument {
+        nodes,
+        source: String::new(),
+    })
+}
+
+/// Parse tokens from [`tokenize_spanned`], recording where each JSX element
+/// sits in `source`.
+pub fn parse_spanned(tokens: Vec<(Token, Range<usize>)>, source: &str) -> Result<MdxDocument, ParseError> {
+    let (tokens, spans) = tokens.into_iter().unzip();
+    let mut parser = Parser::new(tokens, spans);
+    let nodes = parser.parse_nodes(None)?;
+    Ok(MdxDocument {
+        nodes,
+        source: source.to_string(),
+    })
 }
 
 struct Parser {
     tokens: Vec<Token>,
+    /// Source ranges of `tokens`; empty when unknown.
+    spans: Vec<Range<usize>>,
     pos: usize,
 }
 
 impl Parser {
-    fn new(tokens: Vec<Token>) -> Self {
-        Self { tokens, pos: 0 }
+    fn new(tokens: Vec<Token>, spans: Vec<Range<usize>>) -> Self {
+        Self { tokens, spans, pos: 0 }
+    }
+
+    /// Source range from the token at `start` to the last consumed token.
+    fn span_from(&self, start: usize) -> Option<Range<usize>> {
+        Some(self.spans.get(start)?.start..self.spans.get(self.pos - 1)?.end)
     }
 
     fn peek(&self) -> Option<&Token> {
@@ -84,6 +107,7 @@ impl Parser {
     }
 
     fn parse_node(&mut self) -> Result<MdxNode, ParseError> {
+        let start = self.pos;
         let token = self.next().ok_or_else(|| ParseError {
             message: "Unexpected end of input".to_string(),
         })?;
@@ -116,6 +140,7 @@ impl Parser {
                         attributes: attrs,
                         children: vec![],
                         self_closing: true,
+                        span: self.span_from(start),
                     })
                 } else {
                     let children = self.parse_nodes(Some(&tag))?;
@@ -124,6 +149,7 @@ impl Parser {
                         attributes: attrs,
                         children,
                         self_closing: false,
+                        span: self.span_from(start),
                     })
                 }
             }
@@ -171,6 +197,7 @@ mod tests {
                 attributes,
                 self_closing,
                 children,
+                ..
             } => {
                 assert_eq!(tag, "Badge");
                 assert!(self_closing);
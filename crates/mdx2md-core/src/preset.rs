@@ -0,0 +1,91 @@
+use crate::config::{ComponentTransform, Config, ConfigError};
+
+/// Component rules bundled for popular MDX documentation frameworks.
+const PRESETS: &[(&str, &str)] = &[
+    ("docusaurus", include_str!("../presets/docusaurus.toml")),
+    ("mintlify", include_str!("../presets/mintlify.toml")),
+    ("nextra", include_str!("../presets/nextra.toml")),
+    ("starlight", include_str!("../presets/starlight.toml")),
+];
+
+/// Names of the bundled presets.
+pub fn names() -> impl Iterator<Item = &'static str> {
+    PRESETS.iter().map(|(name, _)| *name)
+}
+
+/// Merge the named presets into `config`. Rules and partials already in the
+/// config win, then presets in the order they are listed. Preset rules are
+/// marked [`from_preset`](crate::config::ComponentTransform::from_preset), so
+/// they also lose to the config's `match` patterns and globs.
+pub(crate) fn apply(config: &mut Config) -> Result<(), ConfigError> {
+    for name in config.preset.clone() {
+        let Some((_, source)) = PRESETS.iter().find(|(n, _)| *n == name) else {
+            return Err(ConfigError {
+                message: format!(
+                    "unknown preset {name} (available: {})",
+                    names().collect::<Vec<_>>().join(", ")
+                ),
+            });
+        };
+        let preset: Config = toml::from_str(source).map_err(|e| ConfigError {
+            message: format!("preset {name}: {e}"),
+        })?;
+        for (pattern, rule) in preset.components {
+            config.components.entry(pattern).or_insert(ComponentTransform { from_preset: true, ..rule });
+        }
+        for (partial, template) in preset.partials {
+            config.partials.entry(partial).or_insert(template);
+        }
+        if config.markdown.admonitions.is_none() {
+            config.markdown.admonitions = preset.markdown.admonitions;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::convert;
+
+    #[test]
+    fn test_presets_parse() {
+        for name in names() {
+            let config = Config::from_toml(&format!("preset = [\"{name}\"]")).unwrap();
+            assert!(!config.components.is_empty(), "{name}");
+        }
+        assert!(Config::from_toml("preset = [\"unknown\"]").is_err());
+    }
+
+    #[test]
+    fn test_user_rules_override_preset() {
+        let config = Config::from_toml(
+            r#"
+preset = ["docusaurus"]
+
+[components.TabItem]
+template = "Using {value}:\n\n{children}"
+"#,
+        )
+        .unwrap();
+        let input = "<Tabs>\n<TabItem value=\"npm\">\n\nnpm i\n\n</TabItem>\n</Tabs>\n\n<Admonition type=\"tip\">Hi</Admonition>\n";
+        let result = convert(input, &config).unwrap();
+        assert_eq!(result, "Using npm:\n\nnpm i\n\n> **Tip**\n>\n> Hi\n");
+    }
+
+    #[test]
+    fn test_user_patterns_beat_preset_keys() {
+        let config = Config::from_toml(
+            r#"
+preset = ["mintlify"]
+
+[components.callouts]
+match = ["Note", "Tip*"]
+template = "NOTE: {children}"
+"#,
+        )
+        .unwrap();
+        let result = convert("<Note>hi</Note>\n\n<Tip>there</Tip>\n\n<Info>info</Info>\n", &config).unwrap();
+        assert_eq!(result, "NOTE: hi\n\nNOTE: there\n\n> **Info**\n>\n> info\n");
+    }
+}
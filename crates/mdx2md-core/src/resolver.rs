@@ -0,0 +1,282 @@
+use crate::ast::{JsxElement, MdxNode};
+use crate::config::Config;
+use crate::diagnostic::Diagnostic;
+use crate::transform::{ComponentResolver, RenderContext, Renderer, Rules};
+use crate::value::Value;
+use std::cell::{OnceCell, RefCell};
+use std::collections::HashMap;
+use std::ops::Range;
+
+/// Component resolver with access to the full element.
+///
+/// Unlike [`ComponentResolver`], it receives the [`JsxElement`] (typed props and
+/// the children AST), renders children only when asked through the
+/// [`ResolveContext`], and can report errors and diagnostics. Return
+/// `Ok(None)` to fall back to config rules. An `Err` is recorded as an error
+/// diagnostic and also falls back to config rules.
+pub trait ElementResolver {
+    fn resolve(&self, element: &JsxElement<'_>, cx: &ResolveContext<'_>) -> Result<Option<String>, ResolveError>;
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ResolveError {
+    pub message: String,
+}
+
+impl ResolveError {
+    pub fn new(message: impl Into<String>) -> Self {
+        Self {
+            message: message.into(),
+        }
+    }
+}
+
+impl std::fmt::Display for ResolveError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Resolve error: {}", self.message)
+    }
+}
+
+impl std::error::Error for ResolveError {}
+
+/// Path to a parent element below the resolved one, and a range of its children.
+pub(crate) type SliceKey = (Vec<usize>, Range<usize>);
+
+/// Rendering services available to an [`ElementResolver`] for one element.
+pub struct ResolveContext<'a> {
+    pub(crate) renderer: &'a Renderer<'a>,
+    pub(crate) element: JsxElement<'a>,
+    pub(crate) props: HashMap<String, Value>,
+    pub(crate) context: &'a RenderContext,
+    pub(crate) children: OnceCell<String>,
+    /// Rendered slices of the children or of nested elements' children,
+    /// keyed by the path to their parent and the index range.
+    pub(crate) rendered: RefCell<Vec<(SliceKey, String)>>,
+}
+
+impl<'a> ResolveContext<'a> {
+    /// Where the element sits in the document.
+    pub fn context(&self) -> &RenderContext {
+        self.context
+    }
+
+    /// The element's props after the matching rule's `rename`, `defaults`
+    /// and `map`.
+    pub fn props(&self) -> &HashMap<String, Value> {
+        &self.props
+    }
+
+    /// The element's children rendered to Markdown with the current options.
+    /// Rendering happens on first call and is cached.
+    pub fn render_children(&self) -> String {
+        self.children
+            .get_or_init(|| self.renderer.transform_children(self.element.children, &self.child_ancestors()))
+            .clone()
+    }
+
+    /// Render `children[start..end]` of this element. Each range is rendered
+    /// once and cached, so falling back after rendering parts of the children
+    /// doesn't report their diagnostics twice.
+    pub fn render_child_range(&self, start: usize, end: usize) -> String {
+        self.render_slice(&[], start..end)
+    }
+
+    /// Render the children of a nested element, with that element and its
+    /// parents as ancestors. `path` gives the child index at each level, e.g.
+    /// `[2, 0]` is the first child of this element's third child. Cached like
+    /// [`render_child_range`](Self::render_child_range); an index that doesn't
+    /// lead to an element renders nothing.
+    pub fn render_descendant(&self, path: &[usize]) -> String {
+        let Some((_, children)) = self.descend(path) else {
+            return String::new();
+        };
+        self.render_slice(path, 0..children.len())
+    }
+
+    /// Render any nodes as if they were children of this element, e.g. a
+    /// filtered copy of the children. Not cached.
+    pub fn render_nodes(&self, nodes: &[MdxNode]) -> String {
+        self.renderer.transform_children(nodes, &self.child_ancestors())
+    }
+
+    /// Render the children with different options. The same resolver is used,
+    /// and diagnostics are collected into this conversion.
+    pub fn render_children_with(&self, config: &Config) -> String {
+        // Reuse the compiled rules unless the config is a different one
+        let compiled;
+        let rules = if std::ptr::eq(config, self.renderer.config) {
+            self.renderer.rules
+        } else {
+            match Rules::new(config) {
+                Ok(rules) => {
+                    compiled = rules;
+                    &compiled
+                }
+                Err(e) => {
+                    self.error(e.message);
+                    return String::new();
+                }
+            }
+        };
+        let mut renderer = Renderer::new(config, rules, self.renderer.resolver);
+        renderer.scope = self.renderer.scope.clone();
+        renderer.source = self.renderer.source;
+        let rendered = renderer.transform_children(self.element.children, &self.child_ancestors());
+        for diagnostic in renderer.take_diagnostics() {
+            self.renderer.report(diagnostic);
+        }
+        self.renderer.verbatim.borrow_mut().append(&mut renderer.verbatim.borrow_mut());
+        rendered
+    }
+
+    /// Record a warning about this element.
+    pub fn warn(&self, message: impl Into<String>) {
+        self.renderer
+            .report(Diagnostic::warning(Some(self.element.tag), message));
+    }
+
+    /// Record an error about this element without aborting the resolver.
+    pub fn error(&self, message: impl Into<String>) {
+        self.renderer.report(Diagnostic::error(Some(self.element.tag), message));
+    }
+
+    fn child_ancestors(&self) -> Vec<String> {
+        let mut ancestors = self.context.ancestors.clone();
+        ancestors.push(self.element.tag.to_string());
+        ancestors
+    }
+
+    /// Ancestors and children of the element at `path` below this one.
+    fn descend(&self, path: &[usize]) -> Option<(Vec<String>, &'a [MdxNode])> {
+        let mut ancestors = self.child_ancestors();
+        let mut children = self.element.children;
+        for &index in path {
+            let element = children.get(index)?.as_element()?;
+            ancestors.push(element.tag.to_string());
+            children = element.children;
+        }
+        Some((ancestors, children))
+    }
+
+    fn render_slice(&self, path: &[usize], range: Range<usize>) -> String {
+        let Some((ancestors, children)) = self.descend(path) else {
+            return String::new();
+        };
+        if path.is_empty() && range == (0..children.len()) {
+            return self.render_children();
+        }
+        let Some(nodes) = children.get(range.clone()) else {
+            return String::new();
+        };
+        let key = (path.to_vec(), range);
+        if let Some((_, text)) = self.rendered.borrow().iter().find(|(k, _)| *k == key) {
+            return text.clone();
+        }
+        let text = self.renderer.transform_children(nodes, &ancestors);
+        self.rendered.borrow_mut().push((key, text.clone()));
+        text
+    }
+}
+
+/// Adapts a [`ComponentResolver`] to the [`ElementResolver`] API.
+/// Children are always rendered before the resolver is called.
+pub struct LegacyResolver<'a>(pub &'a dyn ComponentResolver);
+
+impl ElementResolver for LegacyResolver<'_> {
+    fn resolve(&self, element: &JsxElement<'_>, cx: &ResolveContext<'_>) -> Result<Option<String>, ResolveError> {
+        let children = cx.render_children();
+        Ok(self.0.resolve(element.tag, cx.props(), &children, cx.context()))
+    }
+}
+
+/// No-op resolver that always falls back to config.
+pub(crate) struct NoResolver;
+
+impl ElementResolver for NoResolver {
+    fn resolve(&self, _element: &JsxElement<'_>, _cx: &ResolveContext<'_>) -> Result<Option<String>, ResolveError> {
+        Ok(None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diagnostic::Severity;
+    use crate::parser::parse;
+    use crate::tokenizer::tokenize;
+    use crate::transform::transform_with_element_resolver;
+
+    /// Renders `<List>` from its `<Item>` children and rejects other children.
+    struct ListResolver;
+
+    impl ElementResolver for ListResolver {
+        fn resolve(&self, element: &JsxElement<'_>, cx: &ResolveContext<'_>) -> Result<Option<String>, ResolveError> {
+            match element.tag {
+                "List" => {
+                    let mut out = Vec::new();
+                    for (i, item) in element.indexed_child_elements() {
+                        if item.tag != "Item" {
+                            cx.warn(format!("unexpected <{}> inside <List>", item.tag));
+                            continue;
+                        }
+                        out.push(format!("- {}", cx.render_descendant(&[i])));
+                    }
+                    Ok(Some(out.join("\n")))
+                }
+                // Renders its children piecemeal, then falls back
+                "Probe" => {
+                    let len = element.children.len();
+                    cx.render_child_range(0, len);
+                    cx.render_child_range(1, len);
+                    cx.render_child_range(1, len);
+                    Ok(None)
+                }
+                "Trail" => Ok(Some(cx.context().ancestors.join(">"))),
+                "Badge" => match element.prop("count") {
+                    Some(Value::Number(n)) => Ok(Some(format!("({n})"))),
+                    _ => Err(ResolveError::new("count must be a number")),
+                },
+                _ => Ok(None),
+            }
+        }
+    }
+
+    fn run(input: &str, config: &Config) -> (String, Vec<Diagnostic>) {
+        let doc = parse(tokenize(input).unwrap()).unwrap();
+        transform_with_element_resolver(&doc, config, &ListResolver)
+    }
+
+    #[test]
+    fn test_resolver_walks_children_ast() {
+        let (result, diagnostics) = run("<List><Item>one</Item><Item>**two**</Item><Other /></List>", &Config::default());
+        assert_eq!(result.trim(), "- one\n- **two**");
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].severity, Severity::Warning);
+        assert_eq!(diagnostics[0].tag.as_deref(), Some("List"));
+    }
+
+    #[test]
+    fn test_nested_nodes_render_with_their_ancestors() {
+        let (result, _) = run("<List><Item><Trail /></Item></List>", &Config::default());
+        assert_eq!(result.trim(), "- List>Item");
+
+        let config = Config::from_toml("[options]\nhtml_to_markdown = true").unwrap();
+        let (result, _) = run("<ul><li><Trail /></li></ul>\n\n<table><tbody><tr><td><Trail /></td></tr></tbody></table>", &config);
+        assert_eq!(result.trim(), "- ul>li\n\n| table>tbody>tr>td |\n| --- |");
+    }
+
+    #[test]
+    fn test_resolver_error_falls_back() {
+        let config = Config::from_toml("[components.Badge]\ntemplate = \"[{count}]\"").unwrap();
+        let (result, diagnostics) = run("<Badge count={3} /> <Badge count=\"x\" />", &config);
+        assert_eq!(result.trim(), "(3) [x]");
+        assert_eq!(diagnostics, vec![Diagnostic::error(Some("Badge"), "count must be a number")]);
+    }
+
+    #[test]
+    fn test_rendered_nodes_are_cached() {
+        let (result, diagnostics) = run("<Probe><Badge count=\"x\" /> text</Probe>", &Config::default());
+        assert_eq!(result.trim(), "text");
+        assert_eq!(diagnostics, vec![Diagnostic::error(Some("Badge"), "count must be a number")]);
+    }
+}
@@ -1,15 +1,120 @@
 use crate::config::*;
+use crate::serialize::escape_html_attr;
+use crate::slug::Slugger;
+use crate::whitespace::FenceState;
 use pulldown_cmark::{Event, Options, Parser, Tag, TagEnd};
+use std::collections::HashMap;
+use std::ops::Range;
 
 /// Layer 2: Rewrite Markdown structure (tables -> lists, relative -> absolute links,
-/// strip links/images, filter by domain, remove HTML comments, strip DOCTYPE).
+/// strip links/images, filter by domain, remove HTML comments, strip DOCTYPE,
+/// renumber headings, heading ids, table of contents).
 /// Uses pulldown-cmark to locate elements, then does surgical string replacements
 /// to preserve formatting of everything we don't touch.
 pub fn rewrite_markdown(input: &str, config: &Config) -> String {
     let result = strip_doctype(input, config);
     let result = strip_html_comments(&result, config);
+    let result = rewrite_admonitions(&result, config);
     let result = rewrite_links_and_images(&result, config);
-    rewrite_tables(&result, config)
+    let result = rewrite_tables(&result, config);
+    let result = rewrite_headings(&result, config);
+    let result = rewrite_heading_ids(&result, config);
+    insert_toc(&result, config)
+}
+
+/// Where `[markdown.toc]` puts the table of contents. `<TOC />` and
+/// `<TOCInline />` components become this marker in Layer 1.
+pub const TOC_MARKER: &str = "[[toc]]";
+
+/// Turn `:::note` container directives (Docusaurus, Starlight) into
+/// blockquotes. A title can follow the type (`:::tip Title` or
+/// `:::tip[Title]`), and containers nest by using more colons.
+fn rewrite_admonitions(input: &str, config: &Config) -> String {
+    if config.markdown.admonitions != Some(true) {
+        return input.to_string();
+    }
+
+    let mut out: Vec<String> = Vec::new();
+    // Colon counts of the open containers, outermost first
+    let mut open: Vec<usize> = Vec::new();
+    let mut fence = FenceState::default();
+    let mut just_closed = false;
+
+    for line in input.lines() {
+        let prefix = "> ".repeat(open.len());
+        if fence.is_open() {
+            fence.update(line);
+            out.push(quote_line(&prefix, line));
+            continue;
+        }
+        if admonition_close(line).is_some_and(|c| open.last() == Some(&c)) {
+            open.pop();
+            just_closed = true;
+            continue;
+        }
+        if just_closed && !line.trim().is_empty() {
+            out.push(quote_line(&"> ".repeat(open.len()), ""));
+        }
+        just_closed = false;
+        if let Some((colons, label)) = admonition_open(line) {
+            out.push(format!("{prefix}> **{label}**"));
+            out.push(format!("{prefix}>"));
+            open.push(colons);
+            continue;
+        }
+        fence.update(line);
+        out.push(quote_line(&prefix, line));
+    }
+
+    let mut result = out.join("\n");
+    if input.ends_with('\n') {
+        result.push('\n');
+    }
+    result
+}
+
+fn quote_line(prefix: &str, line: &str) -> String {
+    if line.trim().is_empty() {
+        prefix.trim_end().to_string()
+    } else {
+        format!("{prefix}{line}")
+    }
+}
+
+/// Parse `:::type`, `:::type Title` or `:::type[Title]` into the colon count
+/// and the label to show.
+fn admonition_open(line: &str) -> Option<(usize, String)> {
+    let line = line.trim();
+    let colons = line.len() - line.trim_start_matches(':').len();
+    if colons < 3 {
+        return None;
+    }
+    let rest = &line[colons..];
+    let kind_end = rest
+        .find(|c: char| !(c.is_ascii_alphanumeric() || c == '-' || c == '_'))
+        .unwrap_or(rest.len());
+    let kind = &rest[..kind_end];
+    if kind.is_empty() {
+        return None;
+    }
+    let title = rest[kind_end..].trim();
+    let title = title
+        .strip_prefix('[')
+        .and_then(|t| t.strip_suffix(']'))
+        .unwrap_or(title)
+        .trim();
+    let mut label: String = kind[..1].to_uppercase();
+    label.push_str(&kind[1..]);
+    if !title.is_empty() {
+        label = format!("{label}: {title}");
+    }
+    Some((colons, label))
+}
+
+/// A closing line is only colons (at least three).
+fn admonition_close(line: &str) -> Option<usize> {
+    let line = line.trim();
+    (line.len() >= 3 && line.chars().all(|c| c == ':')).then_some(line.len())
 }
 
 /// Rewrite link/image URLs: strip, filter by allowed domains, or make absolute.
@@ -115,8 +220,8 @@ fn domain_allowed(url: &str, allowed: &[String]) -> bool {
 fn extract_host(url: &str) -> String {
     let without_scheme = if let Some(idx) = url.find("://") {
         &url[idx + 3..]
-    } else if url.starts_with("//") {
-        &url[2..]
+    } else if let Some(rest) = url.strip_prefix("//") {
+        rest
     } else {
         url
     };
@@ -353,6 +458,289 @@ fn parse_table_row(line: &str) -> Vec<String> {
         .collect()
 }
 
+/// Renumber ATX and Setext headings per `[markdown.headings]`, optionally
+/// injecting a title above them. Heading text is left as written.
+fn rewrite_headings(input: &str, config: &Config) -> String {
+    let Some(rules) = &config.markdown.headings else {
+        return input.to_string();
+    };
+    let shift = rules.shift + i32::from(rules.title.is_some());
+
+    let mut opts = Options::empty();
+    opts.insert(Options::ENABLE_TABLES);
+    opts.insert(Options::ENABLE_YAML_STYLE_METADATA_BLOCKS);
+
+    let mut headings = Vec::new();
+    let mut body_start = 0;
+    for (event, range) in Parser::new_ext(input, opts).into_offset_iter() {
+        match event {
+            Event::Start(Tag::MetadataBlock(_)) => body_start = range.end,
+            Event::Start(Tag::Heading { level, .. }) => headings.push((level as i32, range)),
+            _ => {}
+        }
+    }
+
+    let mut seen_h1 = rules.title.is_some();
+    let mut edits = Vec::new();
+    for (level, range) in headings {
+        let mut level = (level + shift).clamp(1, 6) as usize;
+        if rules.single_h1 && level == 1 {
+            if seen_h1 {
+                level = 2;
+            }
+            seen_h1 = true;
+        }
+        edits.push((range.clone(), renumber_heading(&input[range], level, rules.setext_to_atx)));
+    }
+
+    let mut result = input.to_string();
+    for (range, heading) in edits.into_iter().rev() {
+        result.replace_range(range, &heading);
+    }
+    if let Some(title) = &rules.title {
+        // Headings all follow the frontmatter, so its offset is unchanged
+        let at = match input[body_start..].find('\n') {
+            Some(i) if input[body_start..body_start + i].trim().is_empty() => body_start + i + 1,
+            _ => body_start,
+        };
+        result.insert_str(at, &format!("# {title}\n\n"));
+    }
+    result
+}
+
+/// Rewrite one heading block at `level`. Setext headings stay Setext while
+/// they fit (levels 1 and 2) unless `setext_to_atx` is set.
+fn renumber_heading(text: &str, level: usize, setext_to_atx: bool) -> String {
+    let body = text.trim_end_matches(['\n', '\r']);
+    let ending = &text[body.len()..];
+    let trimmed = body.trim_start();
+    if trimmed.starts_with('#') {
+        let indent = &body[..body.len() - trimmed.len()];
+        let rest = trimmed.trim_start_matches('#');
+        return format!("{indent}{}{rest}{ending}", "#".repeat(level));
+    }
+
+    let (content, underline) = body.rsplit_once('\n').unwrap_or((body, ""));
+    if !setext_to_atx && level <= 2 {
+        let marker = if level == 1 { "=" } else { "-" };
+        return format!("{content}\n{}{ending}", underline.replace(['=', '-'], marker));
+    }
+    // Continuation lines of a quoted heading carry their own `>` markers
+    let content: Vec<&str> = content
+        .lines()
+        .enumerate()
+        .map(|(i, line)| if i == 0 { line.trim() } else { line.trim_start_matches(['>', ' ', '\t']).trim() })
+        .collect();
+    format!("{} {}{ending}", "#".repeat(level), content.join(" "))
+}
+
+/// Strip `{#custom-id}` heading ids or turn them into `<a id>` anchors.
+/// Stripping repoints `(#custom-id)` links at the heading's GitHub slug.
+fn rewrite_heading_ids(input: &str, config: &Config) -> String {
+    let mode = config.markdown.heading_ids;
+    if mode == HeadingIds::Keep || !input.contains("{#") {
+        return input.to_string();
+    }
+
+    let mut opts = Options::empty();
+    opts.insert(Options::ENABLE_TABLES);
+    opts.insert(Options::ENABLE_YAML_STYLE_METADATA_BLOCKS);
+    opts.insert(Options::ENABLE_HEADING_ATTRIBUTES);
+
+    let mut slugger = Slugger::default();
+    let mut edits: Vec<(Range<usize>, String)> = Vec::new();
+    let mut renamed: HashMap<String, String> = HashMap::new();
+    let mut links: Vec<(Range<usize>, String)> = Vec::new();
+    let mut heading: Option<(Range<usize>, Option<String>, String)> = None;
+
+    for (event, range) in Parser::new_ext(input, opts).into_offset_iter() {
+        match event {
+            Event::Start(Tag::Heading { id, .. }) => {
+                heading = Some((range, id.map(|id| id.to_string()), String::new()));
+            }
+            Event::Text(text) | Event::Code(text) => {
+                if let Some((_, _, heading_text)) = &mut heading {
+                    heading_text.push_str(&text);
+                }
+            }
+            Event::End(TagEnd::Heading(_)) => {
+                let Some((range, id, text)) = heading.take() else { continue };
+                let slug = slugger.slug(&text);
+                let (Some(id), Some(attr)) = (id, heading_attribute(&input[range.clone()])) else {
+                    continue;
+                };
+                edits.push((range.start + attr.start..range.start + attr.end, String::new()));
+                if !is_heading_id(&id) {
+                    continue;
+                }
+                if mode == HeadingIds::Anchor {
+                    let at = range.start + heading_text_start(&input[range]);
+                    edits.push((at..at, format!("<a id=\"{}\"></a>", escape_html_attr(&id))));
+                } else if id != slug {
+                    renamed.insert(id, slug);
+                }
+            }
+            Event::Start(Tag::Link { dest_url, .. }) if dest_url.starts_with('#') => {
+                links.push((range, dest_url[1..].to_string()));
+            }
+            _ => {}
+        }
+    }
+
+    for (range, fragment) in links {
+        let Some(slug) = renamed.get(&fragment) else { continue };
+        if let Some(at) = input[range.clone()].rfind(&format!("](#{fragment}")) {
+            let start = range.start + at + 3;
+            edits.push((start..start + fragment.len(), slug.clone()));
+        }
+    }
+
+    edits.sort_by_key(|(range, _)| range.start);
+    let mut result = input.to_string();
+    for (range, replacement) in edits.into_iter().rev() {
+        result.replace_range(range, &replacement);
+    }
+    result
+}
+
+/// Whether a custom id is safe to use as an anchor or link fragment:
+/// `[A-Za-z0-9_:.-]+`. Other ids are dropped.
+fn is_heading_id(id: &str) -> bool {
+    !id.is_empty() && id.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | ':' | '.' | '-'))
+}
+
+/// The `{...}` attribute block ending a heading's text, with the
+/// whitespace before it.
+fn heading_attribute(heading: &str) -> Option<Range<usize>> {
+    let body = heading.trim_end();
+    // ATX headings are one line; Setext text ends before the underline
+    let text = if body.trim_start().starts_with('#') {
+        body.lines().next()?
+    } else {
+        &body[..body.rfind('\n')?]
+    };
+    let text = text.trim_end();
+    let open = text.rfind('{')?;
+    text.ends_with('}').then(|| text[..open].trim_end().len()..text.len())
+}
+
+/// Offset of the heading text, after any ATX `#` marker.
+fn heading_text_start(heading: &str) -> usize {
+    let trimmed = heading.trim_start();
+    if !trimmed.starts_with('#') {
+        return heading.len() - trimmed.len();
+    }
+    let text = trimmed.trim_start_matches('#').trim_start_matches([' ', '\t']);
+    heading.len() - text.len()
+}
+
+/// Build a table of contents from the headings and put it at each `[[toc]]`
+/// marker, or at the configured position if there are none.
+fn insert_toc(input: &str, config: &Config) -> String {
+    let Some(toc) = &config.markdown.toc else {
+        return input.to_string();
+    };
+
+    let mut opts = Options::empty();
+    opts.insert(Options::ENABLE_TABLES);
+    opts.insert(Options::ENABLE_YAML_STYLE_METADATA_BLOCKS);
+    opts.insert(Options::ENABLE_HEADING_ATTRIBUTES);
+
+    let mut slugger = Slugger::default();
+    let mut entries: Vec<(usize, String, String)> = Vec::new();
+    let mut markers: Vec<Range<usize>> = Vec::new();
+    let mut body_start = 0;
+    let mut opening_h1: Option<usize> = None;
+    // Level, text and explicit anchor of the heading being read
+    let mut heading: Option<(usize, String, Option<String>)> = None;
+
+    for (event, range) in Parser::new_ext(input, opts).into_offset_iter() {
+        match event {
+            Event::Start(Tag::MetadataBlock(_)) => body_start = range.end,
+            Event::Start(Tag::Heading { level, id, .. }) => {
+                if level as usize == 1 && input[body_start..range.start].trim().is_empty() {
+                    opening_h1 = Some(range.end);
+                }
+                heading = Some((level as usize, String::new(), id.filter(|id| is_heading_id(id)).map(|id| id.to_string())));
+            }
+            Event::Text(text) | Event::Code(text) => {
+                if let Some((_, heading_text, _)) = &mut heading {
+                    heading_text.push_str(&text);
+                }
+            }
+            // `heading_ids = "anchor"` leaves `<a id="...">` in the heading
+            Event::InlineHtml(html) => {
+                if let Some((_, _, anchor @ None)) = &mut heading {
+                    *anchor = html
+                        .strip_prefix("<a id=\"")
+                        .and_then(|rest| rest.split_once('"'))
+                        .map(|(id, _)| id.to_string());
+                }
+            }
+            Event::End(TagEnd::Heading(_)) => {
+                let Some((level, text, anchor)) = heading.take() else { continue };
+                let slug = slugger.slug(&text);
+                if (toc.min_depth as usize..=toc.max_depth as usize).contains(&level) {
+                    entries.push((level, text.trim().to_string(), anchor.unwrap_or(slug)));
+                }
+            }
+            Event::Start(Tag::Paragraph) => {
+                let text = input[range.clone()].trim_end();
+                if text.eq_ignore_ascii_case(TOC_MARKER) {
+                    markers.push(range.start..range.start + text.len());
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let list = toc_list(&entries);
+    let mut result = input.to_string();
+    if !markers.is_empty() {
+        for range in markers.into_iter().rev() {
+            result.replace_range(range, &list);
+        }
+        return result;
+    }
+    let at = match toc.position {
+        TocPosition::Marker => return result,
+        TocPosition::Top => opening_h1.unwrap_or(body_start),
+        TocPosition::AfterFrontmatter => body_start,
+    };
+    if list.is_empty() {
+        return result;
+    }
+    // Start on a fresh line and keep blank lines around the list
+    let at = match input[at..].find('\n') {
+        Some(i) if input[at..at + i].trim().is_empty() => at + i + 1,
+        _ => at,
+    };
+    let before = if input[..at].trim().is_empty() || input[..at].ends_with("\n\n") || input[..at].ends_with("---\n") {
+        ""
+    } else {
+        "\n"
+    };
+    let after = if input[at..].starts_with('\n') || input[at..].is_empty() { "\n" } else { "\n\n" };
+    result.insert_str(at, &format!("{before}{list}{after}"));
+    result
+}
+
+/// A nested bullet list of heading links. Items nest under the nearest
+/// shallower heading, so skipped levels don't over-indent.
+fn toc_list(entries: &[(usize, String, String)]) -> String {
+    let mut open: Vec<usize> = Vec::new();
+    let mut lines = Vec::new();
+    for (level, text, anchor) in entries {
+        while open.last().is_some_and(|l| l >= level) {
+            open.pop();
+        }
+        let text = text.replace('[', "\\[").replace(']', "\\]");
+        lines.push(format!("{}- [{text}](#{anchor})", "  ".repeat(open.len())));
+        open.push(*level);
+    }
+    lines.join("\n")
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -753,4 +1141,128 @@ See [docs](/guide) and ![img](/pic.png).
     fn test_domain_not_allowed() {
         assert!(!domain_allowed("https://evil.com/payload", &[String::from("example.com")]));
     }
+
+    #[test]
+    fn test_admonitions_become_blockquotes() {
+        let config = Config::from_toml("[markdown]\nadmonitions = true").unwrap();
+        let input = "::::tip[Pro tip]\nUse it.\n\n```\n:::\n```\n\n:::warning\nCareful\n:::\n::::\nAfter\n";
+        assert_eq!(
+            rewrite_markdown(input, &config),
+            "> **Tip: Pro tip**\n>\n> Use it.\n>\n> ```\n> :::\n> ```\n>\n> > **Warning**\n> >\n> > Careful\n\nAfter\n"
+        );
+        assert_eq!(rewrite_markdown(":::note\nx\n:::\n", &Config::default()), ":::note\nx\n:::\n");
+    }
+
+    #[test]
+    fn test_heading_shift_and_setext() {
+        let config = Config::from_toml("[markdown.headings]\nshift = 1").unwrap();
+        let input = "# Title\n\nIntro\n===\n\n```\n# not a heading\n```\n\n> Quoted\n> ---\n\n###### Deepest ###\n";
+        assert_eq!(
+            rewrite_markdown(input, &config),
+            "## Title\n\nIntro\n---\n\n```\n# not a heading\n```\n\n> ### Quoted\n\n###### Deepest ###\n"
+        );
+
+        let config = Config::from_toml("[markdown.headings]\nsetext_to_atx = true").unwrap();
+        assert_eq!(rewrite_markdown("Two\nlines\n---\n", &config), "## Two lines\n");
+        let config = Config::from_toml("[markdown.headings]\nshift = -1").unwrap();
+        assert_eq!(rewrite_markdown("Sub\n---\n\n# Top\n", &config), "Sub\n===\n\n# Top\n");
+    }
+
+    #[test]
+    fn test_heading_title_and_single_h1() {
+        let config = Config::from_toml("[markdown.headings]\ntitle = \"Guide\"").unwrap();
+        assert_eq!(
+            rewrite_markdown("---\ntitle: x\n---\n# Intro\n\n## Setup\n", &config),
+            "---\ntitle: x\n---\n# Guide\n\n## Intro\n\n### Setup\n"
+        );
+        assert_eq!(rewrite_markdown("Text\n", &config), "# Guide\n\nText\n");
+
+        let config = Config::from_toml("[markdown.headings]\nsingle_h1 = true").unwrap();
+        assert_eq!(
+            rewrite_markdown("# One\n\n## A\n\n# Two\n", &config),
+            "# One\n\n## A\n\n## Two\n"
+        );
+    }
+
+    #[test]
+    fn test_heading_ids() {
+        let input = "See [setup](#install) and [intro](#intro).\n\n## Install the CLI {#install}\n\nIntro\n---\n";
+        let config = Config::from_toml("[markdown]\nheading_ids = \"strip\"").unwrap();
+        assert_eq!(
+            rewrite_markdown(input, &config),
+            "See [setup](#install-the-cli) and [intro](#intro).\n\n## Install the CLI\n\nIntro\n---\n"
+        );
+
+        assert_eq!(
+            rewrite_markdown("## Install {#install}\n", &Config::default()),
+            "## <a id=\"install\"></a>Install\n"
+        );
+        let config = Config::from_toml("[markdown]\nheading_ids = \"keep\"").unwrap();
+        assert_eq!(rewrite_markdown(input, &config), input);
+    }
+
+    #[test]
+    fn test_unsafe_heading_ids_dropped() {
+        let evil = "## Hi {#x\"onmouseover=\"alert(1)}\n";
+        assert_eq!(rewrite_markdown(evil, &Config::default()), "## Hi\n");
+
+        let config = Config::from_toml("[components.Title]\ntemplate = \"## {title}\"\n[components.RawTitle]\ntemplate = \"## {title:raw}\"").unwrap();
+        for tag in ["Title", "RawTitle"] {
+            let input = format!("<{tag} title='Hi {{#x\"onmouseover=\"alert(1)}}' />\n");
+            let out = crate::convert(&input, &config).unwrap();
+            assert!(!out.contains("<a"), "{out}");
+        }
+    }
+
+    #[test]
+    fn test_heading_ids_in_lists_and_setext() {
+        let input = "- ## In list {#inlist}\n\nSetext {#st}\n---\n";
+        assert_eq!(
+            crate::convert(input, &Config::default()).unwrap(),
+            "- ## <a id=\"inlist\"></a>In list\n\n<a id=\"st\"></a>Setext\n---\n"
+        );
+        let config = Config::from_toml("[markdown]\nheading_ids = \"keep\"").unwrap();
+        assert_eq!(crate::convert(input, &config).unwrap(), input);
+        let config = Config::from_toml("[markdown]\nheading_ids = \"strip\"").unwrap();
+        assert_eq!(crate::convert(input, &config).unwrap(), "- ## In list\n\nSetext\n---\n");
+    }
+
+    #[test]
+    fn test_toc_positions() {
+        let input = "---\ntitle: x\n---\n# Guide\n\nIntro\n\n## Install {#setup}\n\n#### From `npm`\n\n## Usage\n\n### Usage\n\n```\n## not a heading\n```\n";
+        let toc = "- [Install](#install)\n  - [From npm](#from-npm)\n- [Usage](#usage)\n  - [Usage](#usage-1)";
+
+        let config = Config::from_toml("[markdown]\nheading_ids = \"strip\"\n[markdown.toc]\nposition = \"top\"\nmax_depth = 4").unwrap();
+        let out = rewrite_markdown(input, &config);
+        assert!(out.starts_with(&format!("---\ntitle: x\n---\n# Guide\n\n{toc}\n\nIntro\n")), "{out}");
+
+        let config = Config::from_toml("[markdown]\nheading_ids = \"keep\"\n[markdown.toc]\nposition = \"after_frontmatter\"\nmax_depth = 4").unwrap();
+        let out = rewrite_markdown(input, &config);
+        assert!(out.starts_with(&format!("---\ntitle: x\n---\n{}\n\n# Guide\n", toc.replace("#install", "#setup"))), "{out}");
+
+        let config = Config::from_toml("[markdown.toc]").unwrap();
+        assert_eq!(rewrite_markdown(input, &config).matches("- [").count(), 0);
+    }
+
+    #[test]
+    fn test_toc_component_rule_wins_over_marker() {
+        let config = Config::from_toml("[markdown.toc]\n[components.TOC]\nmode = \"drop\"\n[components.TOCInline]\ntemplate = \"(contents)\"\n[components._default]\ntemplate = \"{children}\"").unwrap();
+        assert_eq!(
+            crate::convert("# T\n\n<TOC />\n\n<TOCInline />\n\n## A\n", &config).unwrap(),
+            "# T\n\n(contents)\n\n## A\n"
+        );
+
+        // `_default` alone doesn't stop the marker
+        let config = Config::from_toml("[markdown.toc]\n[components._default]\nmode = \"drop\"").unwrap();
+        assert_eq!(crate::convert("# T\n\n<TOC />\n\n## A\n", &config).unwrap(), "# T\n\n- [A](#a)\n\n## A\n");
+    }
+
+    #[test]
+    fn test_toc_marker() {
+        let config = Config::from_toml("[markdown]\nheading_ids = \"anchor\"\n[markdown.toc]").unwrap();
+        assert_eq!(
+            crate::convert("# T\n\n<TOCInline />\n\n## A {#first}\n\n### B\n\n[[TOC]]\n", &config).unwrap(),
+            "# T\n\n- [A](#first)\n  - [B](#b)\n\n## <a id=\"first\"></a>A\n\n### B\n\n- [A](#first)\n  - [B](#b)\n"
+        );
+    }
 }
@@ -0,0 +1,128 @@
+use crate::ast::{MdxDocument, MdxNode};
+use crate::config::Config;
+use crate::value::{parse_literal, Value};
+
+/// Build the variables visible to expressions in a document: `[data]`
+/// sources, config `[variables]`, then `frontmatter`, then literal
+/// `export const`s. Later sources shadow earlier ones.
+pub(crate) fn document_scope(doc: &MdxDocument, config: &Config) -> Value {
+    let mut variables: Vec<(String, Value)> = config
+        .variables
+        .iter()
+        .map(|(name, value)| (name.clone(), value.clone()))
+        .collect();
+    // HashMap order is arbitrary; keep lookups and output deterministic
+    variables.sort_by(|a, b| a.0.cmp(&b.0));
+    let mut entries = config.data_entries();
+    entries.extend(variables);
+
+    for node in &doc.nodes {
+        match node {
+            MdxNode::Frontmatter(yaml) => {
+                if let Ok(value) = serde_yaml::from_str::<Value>(yaml) {
+                    entries.push(("frontmatter".to_string(), value));
+                }
+            }
+            MdxNode::Export(source) => {
+                if let Some(binding) = parse_export(source) {
+                    entries.push(binding);
+                }
+            }
+            _ => {}
+        }
+    }
+    Value::Object(entries)
+}
+
+/// `export const name = <literal>;`
+fn parse_export(source: &str) -> Option<(String, Value)> {
+    let rest = source.trim().strip_prefix("export")?.trim_start();
+    let rest = ["const", "let", "var"]
+        .iter()
+        .find_map(|kw| rest.strip_prefix(kw).filter(|r| r.starts_with(char::is_whitespace)))?;
+    let (name, value) = rest.split_once('=')?;
+    let name = name.trim();
+    if !is_identifier(name) {
+        return None;
+    }
+    let value = value.trim().trim_end_matches(';');
+    Some((name.to_string(), parse_literal(value)?))
+}
+
+/// Look up an identifier or member path (`version`, `frontmatter.title`,
+/// `versions[0]`, `meta["og:title"]`). Returns `None` for anything else,
+/// including paths that don't resolve.
+pub(crate) fn lookup<'v>(scope: &'v Value, expr: &str) -> Option<&'v Value> {
+    let mut current = scope;
+    for segment in member_path(expr.trim())? {
+        current = current.get(&segment)?;
+    }
+    Some(current)
+}
+
+fn member_path(expr: &str) -> Option<Vec<String>> {
+    let mut segments = Vec::new();
+    let mut rest = expr;
+    let ident_end = |s: &str| s.find(|c: char| !(c.is_alphanumeric() || c == '_' || c == '$')).unwrap_or(s.len());
+
+    let end = ident_end(rest);
+    if !is_identifier(&rest[..end]) {
+        return None;
+    }
+    segments.push(rest[..end].to_string());
+    rest = &rest[end..];
+
+    while !rest.is_empty() {
+        if let Some(after) = rest.strip_prefix('.') {
+            let end = ident_end(after);
+            if !is_identifier(&after[..end]) {
+                return None;
+            }
+            segments.push(after[..end].to_string());
+            rest = &after[end..];
+        } else if let Some(after) = rest.strip_prefix('[') {
+            let end = after.find(']')?;
+            let key = after[..end].trim();
+            let key = match parse_literal(key)? {
+                Value::String(s) => s,
+                Value::Number(n) if n >= 0.0 && n.fract() == 0.0 => (n as usize).to_string(),
+                _ => return None,
+            };
+            segments.push(key);
+            rest = &after[end + 1..];
+        } else {
+            return None;
+        }
+    }
+    Some(segments)
+}
+
+fn is_identifier(s: &str) -> bool {
+    let mut chars = s.chars();
+    chars.next().is_some_and(|c| c.is_alphabetic() || c == '_' || c == '$')
+        && chars.all(|c| c.is_alphanumeric() || c == '_' || c == '$')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse;
+    use crate::tokenizer::tokenize;
+
+    #[test]
+    fn test_document_scope_and_lookup() {
+        let config = Config::from_toml("[variables]\nversion = \"1.0\"\nsite = { url = \"https://a.dev\" }").unwrap();
+        let input = "---\ntitle: Hello\ntags: [a, b]\n---\nexport const version = '2.0';\nexport const meta = { 'og:title': \"T\" };\n\n# x\n";
+        let scope = document_scope(&parse(tokenize(input).unwrap()).unwrap(), &config);
+
+        let get = |expr| lookup(&scope, expr).map(|v| v.to_string());
+        assert_eq!(get("version").as_deref(), Some("2.0"));
+        assert_eq!(get("site.url").as_deref(), Some("https://a.dev"));
+        assert_eq!(get(" frontmatter.title ").as_deref(), Some("Hello"));
+        assert_eq!(get("frontmatter.tags[1]").as_deref(), Some("b"));
+        assert_eq!(get("meta['og:title']").as_deref(), Some("T"));
+        assert_eq!(get("missing"), None);
+        assert_eq!(get("version + 1"), None);
+        assert_eq!(get("fn()"), None);
+    }
+}
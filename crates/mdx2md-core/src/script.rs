@@ -0,0 +1,263 @@
+use crate::ast::JsxElement;
+use crate::config::Config;
+use crate::matcher::ComponentMatcher;
+use crate::resolver::{ElementResolver, ResolveContext, ResolveError};
+use crate::transform::RenderContext;
+use crate::value::Value;
+use rhai::{Dynamic, Engine, Scope, AST};
+use std::path::Path;
+
+/// Runs Rhai scripts attached to component rules (`script` or `script_file`).
+///
+/// Scripts see `tag`, `props` (typed values), `children` (rendered Markdown)
+/// and `context` (`parent`, `ancestors`, `depth`, `index`, `position`,
+/// `count`). A script returns the rendered string, or `()` to fall back to the
+/// rule's template. Scripts are sandboxed: no modules, no `eval`, no I/O, and
+/// bounded operations, call depth and data sizes.
+pub struct ScriptResolver {
+    engine: Engine,
+    /// `Some(index)` for rules with a script; `None` for template-only rules,
+    /// so a more specific template rule still wins over a scripted glob.
+    rules: ComponentMatcher<Option<usize>>,
+    /// Patterns of rules merged from presets, which never have scripts.
+    presets: ComponentMatcher<Option<usize>>,
+    scripts: Vec<AST>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScriptError {
+    pub message: String,
+}
+
+impl std::fmt::Display for ScriptError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Script error: {}", self.message)
+    }
+}
+
+impl std::error::Error for ScriptError {}
+
+/// Upper bound on operations per script run, to stop runaway loops.
+const MAX_OPERATIONS: u64 = 200_000;
+
+impl ScriptResolver {
+    /// Compile every component script in the config. `script_file` paths are
+    /// resolved relative to `base_dir` (usually the config file's directory).
+    pub fn from_config(config: &Config, base_dir: Option<&Path>) -> Result<Self, ScriptError> {
+        let engine = sandboxed_engine();
+        let mut keys: Vec<&String> = config.components.keys().collect();
+        keys.sort();
+
+        let mut scripts = Vec::new();
+        let mut rules = ComponentMatcher::new();
+        let mut presets = ComponentMatcher::new();
+        for key in keys {
+            let rule = &config.components[key];
+            if rule.from_preset {
+                for pattern in std::iter::once(key).chain(&rule.matches) {
+                    presets.insert(pattern, None).map_err(|e| ScriptError { message: e.message })?;
+                }
+                continue;
+            }
+            let source = match (&rule.script, &rule.script_file) {
+                (Some(src), _) => Some(src.clone()),
+                (None, Some(file)) => {
+                    let path = base_dir.map_or_else(|| file.into(), |dir| dir.join(file));
+                    Some(std::fs::read_to_string(&path).map_err(|e| ScriptError {
+                        message: format!("reading {}: {e}", path.display()),
+                    })?)
+                }
+                (None, None) => None,
+            };
+            let index = match source {
+                Some(src) => {
+                    let ast = engine.compile(&src).map_err(|e| ScriptError {
+                        message: format!("in component {key}: {e}"),
+                    })?;
+                    scripts.push(ast);
+                    Some(scripts.len() - 1)
+                }
+                None => None,
+            };
+            for pattern in std::iter::once(key).chain(&rule.matches) {
+                rules.insert(pattern, index).map_err(|e| ScriptError { message: e.message })?;
+            }
+        }
+
+        Ok(Self {
+            engine,
+            rules,
+            presets,
+            scripts,
+        })
+    }
+
+    /// Whether any component rule has a script.
+    pub fn is_empty(&self) -> bool {
+        self.scripts.is_empty()
+    }
+}
+
+impl ElementResolver for ScriptResolver {
+    fn resolve(&self, element: &JsxElement<'_>, cx: &ResolveContext<'_>) -> Result<Option<String>, ResolveError> {
+        let tag = element.tag;
+        let found = self
+            .rules
+            .find_pattern(tag)
+            .or_else(|| self.presets.find_pattern(tag))
+            .or_else(|| self.rules.find(tag));
+        let Some(Some(index)) = found else {
+            return Ok(None);
+        };
+
+        let props: rhai::Map = cx
+            .props()
+            .iter()
+            .map(|(k, v)| (k.as_str().into(), to_dynamic(v)))
+            .collect();
+
+        let mut scope = Scope::new();
+        scope.push_constant("tag", element.tag.to_string());
+        scope.push_constant("props", props);
+        scope.push_constant("children", cx.render_children());
+        scope.push_constant("context", context_map(cx.context()));
+
+        let result = self
+            .engine
+            .eval_ast_with_scope::<Dynamic>(&mut scope, &self.scripts[*index])
+            .map_err(|e| ResolveError::new(format!("script failed: {e}")))?;
+
+        if result.is_unit() {
+            Ok(None)
+        } else {
+            Ok(Some(result.to_string()))
+        }
+    }
+}
+
+fn sandboxed_engine() -> Engine {
+    let mut engine = Engine::new();
+    engine.set_module_resolver(rhai::module_resolvers::DummyModuleResolver::new());
+    engine.disable_symbol("eval");
+    engine.on_print(|_| {});
+    engine.on_debug(|_, _, _| {});
+    engine.set_max_operations(MAX_OPERATIONS);
+    engine.set_max_call_levels(32);
+    engine.set_max_expr_depths(64, 32);
+    engine.set_max_string_size(1 << 20);
+    engine.set_max_array_size(10_000);
+    engine.set_max_map_size(10_000);
+    engine
+}
+
+fn context_map(context: &RenderContext) -> rhai::Map {
+    let mut map = rhai::Map::new();
+    map.insert(
+        "parent".into(),
+        context.parent().map_or(Dynamic::UNIT, |p| p.to_string().into()),
+    );
+    map.insert(
+        "ancestors".into(),
+        Dynamic::from_array(context.ancestors.iter().map(|a| a.clone().into()).collect()),
+    );
+    map.insert("depth".into(), (context.depth() as rhai::INT).into());
+    map.insert("index".into(), (context.index as rhai::INT).into());
+    map.insert("position".into(), (context.index as rhai::INT + 1).into());
+    map.insert("count".into(), (context.sibling_count as rhai::INT).into());
+    map
+}
+
+fn to_dynamic(value: &Value) -> Dynamic {
+    match value {
+        Value::Null => Dynamic::UNIT,
+        Value::Bool(b) => (*b).into(),
+        Value::Number(n) if n.fract() == 0.0 && n.abs() < 9e15 => (*n as rhai::INT).into(),
+        Value::Number(n) => (*n).into(),
+        Value::String(s) | Value::Expression(s) => s.clone().into(),
+        Value::Array(items) => Dynamic::from_array(items.iter().map(to_dynamic).collect()),
+        Value::Object(entries) => Dynamic::from_map(
+            entries
+                .iter()
+                .map(|(k, v)| (k.as_str().into(), to_dynamic(v)))
+                .collect(),
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::convert_with_element_resolver;
+
+    fn run(toml: &str, input: &str) -> crate::Conversion {
+        let config = Config::from_toml(toml).unwrap();
+        let resolver = ScriptResolver::from_config(&config, None).unwrap();
+        convert_with_element_resolver(input, &config, &resolver).unwrap()
+    }
+
+    #[test]
+    fn test_script_renders_props_and_children() {
+        let result = run(
+            r#"
+[components.Features]
+script = '''
+let out = "";
+for item in props.items { out += "- " + item + "\n"; }
+out + children
+'''
+"#,
+            r#"<Features items={["Fast", "Safe"]}>**More** soon</Features>"#,
+        );
+        assert_eq!(result.markdown, "- Fast\n- Safe\n**More** soon\n");
+    }
+
+    #[test]
+    fn test_script_context_and_fallback() {
+        let result = run(
+            r#"
+[components.Step]
+script = 'if props.skip == true { () } else { `${context.position}. ${children}` }'
+template = "(skipped)"
+"#,
+            "<Step>One</Step>\n\n<Step skip>Two</Step>\n\n<Step>Three</Step>",
+        );
+        assert_eq!(result.markdown, "1. One\n\n(skipped)\n\n3. Three\n");
+    }
+
+    #[test]
+    fn test_more_specific_template_rule_wins() {
+        let result = run(
+            r#"
+[components."Icon*"]
+script = '"[" + tag + "]"'
+
+[components.IconLogo]
+template = "LOGO"
+"#,
+            "<IconStar /> <IconLogo />",
+        );
+        assert_eq!(result.markdown, "[IconStar] LOGO\n");
+    }
+
+    #[test]
+    fn test_runaway_script_is_stopped() {
+        let result = run(
+            r#"
+[components.Loop]
+script = 'loop {}'
+template = "fallback"
+"#,
+            "<Loop />",
+        );
+        assert_eq!(result.markdown, "fallback\n");
+        assert_eq!(result.diagnostics.len(), 1);
+        assert!(result.diagnostics[0].message.contains("script failed"));
+    }
+
+    #[test]
+    fn test_compile_error_reported() {
+        let config = Config::from_toml("[components.Bad]\nscript = 'let = ;'").unwrap();
+        let err = ScriptResolver::from_config(&config, None).err().unwrap();
+        assert!(err.message.contains("in component Bad"));
+    }
+}
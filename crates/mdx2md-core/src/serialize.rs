@@ -0,0 +1,183 @@
+use crate::ast::*;
+use crate::value::Value;
+
+/// MDX source of a node, used by `mode = "raw"`. Elements parsed from
+/// `source` are copied verbatim; others (e.g. from HTML input) are
+/// serialized.
+pub(crate) fn to_mdx(node: &MdxNode, source: &str) -> String {
+    let mut out = String::new();
+    write_mdx(node, source, &mut out);
+    out
+}
+
+fn write_mdx(node: &MdxNode, source: &str, out: &mut String) {
+    if let MdxNode::JsxElement { span: Some(span), .. } = node {
+        if let Some(text) = source.get(span.clone()) {
+            out.push_str(text);
+            return;
+        }
+    }
+    match node {
+        MdxNode::Frontmatter(content) => {
+            out.push_str("---\n");
+            out.push_str(content);
+            out.push_str("\n---\n");
+        }
+        MdxNode::Import(s) | MdxNode::Export(s) => {
+            out.push_str(s);
+            out.push('\n');
+        }
+        MdxNode::Markdown(content) => out.push_str(content),
+        MdxNode::Expression(content) => {
+            out.push('{');
+            out.push_str(content);
+            out.push('}');
+        }
+        MdxNode::JsxElement {
+            tag,
+            attributes,
+            children,
+            self_closing,
+            ..
+        } => {
+            out.push('<');
+            out.push_str(tag);
+            for attr in attributes {
+                out.push(' ');
+                out.push_str(&attr.name);
+                match &attr.value {
+                    // JSX strings have no escapes, so a value with both
+                    // quote kinds becomes a JS string expression
+                    Some(AttrValue::String(s)) if s.contains('"') && s.contains('\'') => {
+                        out.push_str("={");
+                        out.push_str(&js_string(s));
+                        out.push('}');
+                    }
+                    Some(AttrValue::String(s)) => {
+                        let quote = if s.contains('"') { '\'' } else { '"' };
+                        out.push('=');
+                        out.push(quote);
+                        out.push_str(s);
+                        out.push(quote);
+                    }
+                    Some(AttrValue::Expression(e)) => {
+                        out.push_str("={");
+                        out.push_str(e);
+                        out.push('}');
+                    }
+                    None => {}
+                }
+            }
+            if *self_closing {
+                out.push_str(" />");
+            } else {
+                out.push('>');
+                for child in children {
+                    write_mdx(child, source, out);
+                }
+                out.push_str("</");
+                out.push_str(tag);
+                out.push('>');
+            }
+        }
+    }
+}
+
+/// A double-quoted JS string literal.
+fn js_string(s: &str) -> String {
+    let mut out = String::from('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+/// Build an HTML tag from a JSX element's props. Used by `mode = "html"`.
+/// `className`/`htmlFor` become `class`/`for`, `true` props become bare
+/// attributes, and `false`/`null` or non-literal expression props are omitted.
+pub(crate) fn html_open_tag(tag: &str, props: &[(String, Value)], self_closing: bool) -> String {
+    let mut out = format!("<{tag}");
+    for (name, value) in props {
+        let name = match name.as_str() {
+            "className" => "class",
+            "htmlFor" => "for",
+            other => other,
+        };
+        match value {
+            Value::Bool(true) => {
+                out.push(' ');
+                out.push_str(name);
+            }
+            Value::Bool(false) | Value::Null | Value::Expression(_) => {}
+            value => {
+                out.push(' ');
+                out.push_str(name);
+                out.push_str("=\"");
+                out.push_str(&escape_html_attr(&value.to_string()));
+                out.push('"');
+            }
+        }
+    }
+    out.push_str(if self_closing { " />" } else { ">" });
+    out
+}
+
+pub(crate) fn escape_html_attr(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('"', "&quot;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse;
+    use crate::tokenizer::tokenize;
+
+    fn first_node(input: &str) -> MdxNode {
+        parse(tokenize(input).unwrap()).unwrap().nodes.remove(0)
+    }
+
+    #[test]
+    fn test_to_mdx_roundtrip() {
+        let input = r#"<Card title="Hi" items={[1, 2]} open>Some *text* {value}<Icon name='say "hi"' /></Card>"#;
+        let expected = r#"<Card title="Hi" items={[1, 2]} open>Some *text* {value}<Icon name='say "hi"' /></Card>"#;
+        assert_eq!(to_mdx(&first_node(input), ""), expected);
+    }
+
+    #[test]
+    fn test_to_mdx_verbatim_and_mixed_quotes() {
+        let input = "<Card\n  title='Hi'   open\n>\n  Some *text*  {value}\n</Card>";
+        let tokens = crate::tokenizer::tokenize_spanned(input, &[] as &[&str]).unwrap();
+        let doc = crate::parser::parse_spanned(tokens, input).unwrap();
+        assert_eq!(to_mdx(&doc.nodes[0], &doc.source), input);
+
+        let mut node = first_node("<Q say='x' />");
+        if let MdxNode::JsxElement { attributes, .. } = &mut node {
+            attributes[0].value = Some(AttrValue::String("it's \"a\\b\"".to_string()));
+        }
+        assert_eq!(to_mdx(&node, ""), r#"<Q say={"it's \"a\\b\""} />"#);
+    }
+
+    #[test]
+    fn test_html_open_tag() {
+        let node = first_node(r#"<details className="note" open={true} hidden={false} data={x} title="a&b" />"#);
+        let MdxNode::JsxElement { attributes, .. } = node else {
+            panic!("Expected JsxElement");
+        };
+        let props: Vec<(String, Value)> = attributes.iter().map(|a| (a.name.clone(), a.typed_value())).collect();
+        assert_eq!(
+            html_open_tag("details", &props, false),
+            r#"<details class="note" open title="a&amp;b">"#
+        );
+    }
+}
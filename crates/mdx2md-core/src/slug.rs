@@ -0,0 +1,53 @@
+use std::collections::HashMap;
+
+/// GitHub's heading anchor for `text`: lowercased, punctuation removed and
+/// spaces turned into hyphens.
+pub(crate) fn slugify(text: &str) -> String {
+    text.trim()
+        .to_lowercase()
+        .chars()
+        .filter_map(|c| match c {
+            ' ' => Some('-'),
+            '-' | '_' => Some(c),
+            c if c.is_alphanumeric() => Some(c),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Slugs for the headings of one document, with repeats suffixed `-1`,
+/// `-2`, ... like GitHub (and `github-slugger`).
+#[derive(Debug, Default)]
+pub(crate) struct Slugger {
+    seen: HashMap<String, usize>,
+}
+
+impl Slugger {
+    pub(crate) fn slug(&mut self, text: &str) -> String {
+        let base = slugify(text);
+        let mut slug = base.clone();
+        while self.seen.contains_key(&slug) {
+            let count = self.seen.entry(base.clone()).or_default();
+            *count += 1;
+            slug = format!("{base}-{count}");
+        }
+        self.seen.insert(slug.clone(), 0);
+        slug
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_github_slugs() {
+        assert_eq!(slugify("Hello, World!"), "hello-world");
+        assert_eq!(slugify("  `config.toml` & CLI_flags "), "configtoml--cli_flags");
+        assert_eq!(slugify("Über Café"), "über-café");
+
+        let mut slugger = Slugger::default();
+        let slugs: Vec<String> = ["Intro", "Intro", "Intro 1", "Intro"].iter().map(|t| slugger.slug(t)).collect();
+        assert_eq!(slugs, ["intro", "intro-1", "intro-1-1", "intro-2"]);
+    }
+}
@@ -0,0 +1,138 @@
+use crate::ast::{MdxDocument, MdxNode};
+use crate::config::{Config, FilterAction, FilterRule};
+use crate::matcher::{ComponentMatcher, PatternError};
+use crate::value::Value;
+use std::collections::HashMap;
+
+/// `[[filters]]` evaluated against the build `[targets]`.
+pub(crate) struct TargetFilter<'a> {
+    targets: &'a HashMap<String, Value>,
+    rules: Vec<(Option<ComponentMatcher<()>>, &'a FilterRule)>,
+}
+
+impl<'a> TargetFilter<'a> {
+    pub(crate) fn new(config: &'a Config) -> Result<Self, PatternError> {
+        let mut rules = Vec::new();
+        for rule in &config.filters {
+            let matcher = match &rule.component {
+                Some(pattern) => {
+                    let mut matcher = ComponentMatcher::new();
+                    matcher.insert(pattern, ())?;
+                    Some(matcher)
+                }
+                None => None,
+            };
+            rules.push((matcher, rule));
+        }
+        Ok(Self {
+            targets: &config.targets,
+            rules,
+        })
+    }
+
+    /// Whether an element survives the component rules.
+    pub(crate) fn keeps_element(&self, tag: &str, props: impl Fn(&str) -> Option<Value>) -> bool {
+        self.rules.iter().all(|(matcher, rule)| {
+            let Some(matcher) = matcher else { return true };
+            if matcher.find_pattern(tag).is_none() {
+                return true;
+            }
+            match &rule.prop {
+                Some(prop) => match props(prop) {
+                    Some(value) => self.keeps(rule, prop, &value),
+                    None => true,
+                },
+                None => rule.action == FilterAction::Keep,
+            }
+        })
+    }
+
+    /// Whether a document survives the frontmatter rules.
+    pub(crate) fn keeps_document(&self, doc: &MdxDocument) -> bool {
+        if !self.rules.iter().any(|(_, rule)| rule.frontmatter.is_some()) {
+            return true;
+        }
+        let frontmatter = doc.nodes.iter().find_map(|node| match node {
+            MdxNode::Frontmatter(yaml) => serde_yaml::from_str::<Value>(yaml).ok(),
+            _ => None,
+        });
+        let Some(frontmatter) = frontmatter else { return true };
+        self.rules.iter().all(|(_, rule)| match &rule.frontmatter {
+            Some(key) => match frontmatter.get(key) {
+                Some(value) => self.keeps(rule, key, value),
+                None => true,
+            },
+            None => true,
+        })
+    }
+
+    /// Apply a rule to a value. Rules whose target isn't set don't apply.
+    fn keeps(&self, rule: &FilterRule, default_target: &str, value: &Value) -> bool {
+        let target = rule.target.as_deref().unwrap_or(default_target);
+        let Some(wanted) = self.targets.get(target) else {
+            return true;
+        };
+        let wanted = words(wanted);
+        let matched = words(value).iter().any(|word| wanted.contains(word));
+        matched == (rule.action == FilterAction::Keep)
+    }
+}
+
+/// The values of a target or prop: arrays, or strings separated by commas
+/// or whitespace (`"linux, mac"`).
+fn words(value: &Value) -> Vec<String> {
+    match value {
+        Value::Array(items) => items.iter().flat_map(words).collect(),
+        other => other
+            .to_string()
+            .split(|c: char| c == ',' || c.is_whitespace())
+            .filter(|w| !w.is_empty())
+            .map(str::to_string)
+            .collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::Config;
+    use crate::convert;
+
+    const CONFIG: &str = r#"
+[targets]
+platform = "linux"
+audience = ["public", "beta"]
+
+[[filters]]
+component = "Only"
+prop = "platform"
+
+[[filters]]
+component = "IfAudience"
+prop = "is"
+target = "audience"
+
+[[filters]]
+component = "Internal*"
+action = "drop"
+
+[[filters]]
+frontmatter = "audience"
+"#;
+
+    #[test]
+    fn test_component_filters() {
+        let config = Config::from_toml(CONFIG).unwrap();
+        let input = "<Only platform=\"linux, mac\">Linux</Only>\n\n<Only platform=\"windows\">Windows</Only>\n\n<IfAudience is=\"admin\">Admin</IfAudience>\n\n<IfAudience is={[\"beta\"]}>Beta</IfAudience>\n\n<InternalNote>Secret</InternalNote>\n\n<Only>Always</Only>\n";
+        assert_eq!(convert(input, &config).unwrap(), "Linux\n\nBeta\n\nAlways\n");
+    }
+
+    #[test]
+    fn test_frontmatter_filter() {
+        let config = Config::from_toml(CONFIG).unwrap();
+        assert_eq!(convert("---\naudience: internal\n---\n# Hidden\n", &config).unwrap(), "");
+        assert_eq!(
+            convert("---\naudience: [public]\n---\n# Shown\n", &config).unwrap(),
+            "---\naudience: [public]\n---\n# Shown\n"
+        );
+    }
+}
@@ -0,0 +1,622 @@
+use crate::config::Escape;
+use crate::value::{parse_literal, Value};
+use pulldown_cmark::{Event, Parser, TagEnd};
+use std::collections::HashMap;
+
+/// Replace `{prop}` placeholders in a template with prop values,
+/// and `{children}` with the rendered children string.
+///
+/// Prop placeholders accept a dotted path into arrays/objects (`{items.0}`,
+/// `{meta.title}`), where `[prop]` segments are keyed by another prop's
+/// value (`{$data.glossary[id]}`), and `:`-separated modifiers
+/// (`{items:join(" | ")}`, `{items:list}`, `{rows:table}`). Placeholders
+/// that don't resolve are left untouched.
+///
+/// Prop values are escaped with `escape` unless the placeholder names its own
+/// mode (`{title:link_text}`, `{href:url}`, `{name:code}`, `{label:cell}`,
+/// `{html:raw}`). Placeholders inside a code span of the template
+/// (`` `{name}` ``) default to `raw`, since escapes aren't interpreted there.
+/// Values that span lines get the line's block prefix, so they
+/// can't break out of a blockquote.
+///
+/// Every `{children}` is replaced, and children accept their own modifiers:
+/// `inline` (one line, with `|` escaped for table cells), `text`, `quoted`
+/// and `indent(n)` (counted from the start of the template line). When
+/// children expand to multiple lines and the template line has a prefix
+/// before the placeholder (e.g. `> `), that prefix is applied to all
+/// continuation lines.
+pub(crate) fn apply_template(template: &str, props: &HashMap<String, Value>, children: &str, escape: Escape) -> String {
+    apply_template_with_data(template, props, None, children, escape)
+}
+
+/// [`apply_template`] with `{$data...}` placeholders looking into `data`,
+/// which is shared rather than copied into every element's props.
+pub(crate) fn apply_template_with_data(
+    template: &str,
+    props: &HashMap<String, Value>,
+    data: Option<&Value>,
+    children: &str,
+    escape: Escape,
+) -> String {
+    // Substitute every placeholder in a single pass, so values are never
+    // re-scanned for placeholders.
+    let mut out = String::with_capacity(template.len() + children.len());
+    let mut rest = template;
+
+    while let Some(open) = rest.find('{') {
+        out.push_str(&rest[..open]);
+        let candidate = &rest[open..];
+        let rendered = find_placeholder_end(candidate).and_then(|end| {
+            let placeholder = parse_placeholder(&candidate[1..end])?;
+            let value = if placeholder.path == "children" {
+                let line_start = out.rfind('\n').map_or(0, |p| p + 1);
+                let indented = placeholder.modifiers.iter().any(|m| m.name == "indent");
+                let line = &out[line_start..];
+                if indented && line.trim_start_matches([' ', '\t']).is_empty() {
+                    // `indent(n)` counts from the start of the template line,
+                    // so indentation before the placeholder is part of it
+                    // rather than added to every line again.
+                    out.truncate(line_start);
+                }
+                render_children(children, &placeholder.modifiers)
+            } else {
+                // Backslash escapes would show up literally inside a code span
+                let at = template.len() - candidate.len();
+                let in_code = in_code_span(&template[..at], &candidate[end + 1..]);
+                render_placeholder(&placeholder, props, data, if in_code { Escape::Raw } else { escape })?
+            };
+            Some((value, end))
+        });
+        match rendered {
+            Some((value, end)) => {
+                // Determine the block prefix: leading `> ` or whitespace from the
+                // line containing the placeholder
+                let line_start = out.rfind('\n').map_or(0, |p| p + 1);
+                let prefix = extract_block_prefix(&out[line_start..]);
+                out.push_str(&prefix_lines(&value, &prefix));
+                rest = &candidate[end + 1..];
+            }
+            None => {
+                out.push('{');
+                rest = &candidate[1..];
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+/// Whether a placeholder between `before` and `after` sits inside a code
+/// span of the template: an unmatched backtick run before it that is closed
+/// by a run of the same length after it.
+fn in_code_span(before: &str, after: &str) -> bool {
+    let mut open = None;
+    for run in backtick_runs(before) {
+        open = match open {
+            Some(n) if n == run => None,
+            Some(n) => Some(n),
+            None => Some(run),
+        };
+    }
+    open.is_some_and(|n| backtick_runs(after).any(|run| run == n))
+}
+
+/// Lengths of the runs of backticks in `s`, in order.
+fn backtick_runs(s: &str) -> impl Iterator<Item = usize> + '_ {
+    s.split(|c| c != '`').map(str::len).filter(|&n| n > 0)
+}
+
+/// Find the `}` closing a placeholder that starts at `s[0] == '{'`,
+/// skipping over quoted modifier arguments.
+fn find_placeholder_end(s: &str) -> Option<usize> {
+    let mut quote: Option<char> = None;
+    for (i, c) in s.char_indices().skip(1) {
+        match quote {
+            Some(q) if c == q => quote = None,
+            Some(_) => {}
+            None => match c {
+                '"' | '\'' => quote = Some(c),
+                '}' => return Some(i),
+                '{' | '\n' => return None,
+                _ => {}
+            },
+        }
+    }
+    None
+}
+
+struct Placeholder<'a> {
+    path: &'a str,
+    modifiers: Vec<Modifier>,
+}
+
+struct Modifier {
+    name: String,
+    args: Vec<String>,
+}
+
+/// Parse `path(:modifier(args))*`. Returns `None` if the text is not a
+/// placeholder (e.g. a literal `{` in the template).
+fn parse_placeholder(inner: &str) -> Option<Placeholder<'_>> {
+    let path_end = inner
+        .find(|c: char| !(c.is_alphanumeric() || matches!(c, '_' | '$' | '.' | '-' | '[' | ']')))
+        .unwrap_or(inner.len());
+    let path = &inner[..path_end];
+    if !path.starts_with(|c: char| c.is_alphabetic() || c == '_' || c == '$') {
+        return None;
+    }
+
+    let mut modifiers = Vec::new();
+    let mut rest = &inner[path_end..];
+    while let Some(after_colon) = rest.strip_prefix(':') {
+        let name_end = after_colon
+            .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+            .unwrap_or(after_colon.len());
+        let name = &after_colon[..name_end];
+        if name.is_empty() {
+            return None;
+        }
+        rest = &after_colon[name_end..];
+        let mut args = Vec::new();
+        if let Some(after_paren) = rest.strip_prefix('(') {
+            let close = find_unquoted(after_paren, ')')?;
+            args = split_args(&after_paren[..close]);
+            rest = &after_paren[close + 1..];
+        }
+        modifiers.push(Modifier {
+            name: name.to_string(),
+            args,
+        });
+    }
+
+    rest.is_empty().then_some(Placeholder { path, modifiers })
+}
+
+fn find_unquoted(s: &str, target: char) -> Option<usize> {
+    let mut quote: Option<char> = None;
+    for (i, c) in s.char_indices() {
+        match quote {
+            Some(q) if c == q => quote = None,
+            Some(_) => {}
+            None if c == '"' || c == '\'' => quote = Some(c),
+            None if c == target => return Some(i),
+            None => {}
+        }
+    }
+    None
+}
+
+/// Split a modifier argument list on top-level commas. Quoted arguments are
+/// parsed as JS string literals; anything else is taken as trimmed text.
+fn split_args(args: &str) -> Vec<String> {
+    let mut parts = Vec::new();
+    let mut current = String::new();
+    let mut quote: Option<char> = None;
+    for c in args.chars() {
+        match quote {
+            Some(q) => {
+                if c == q {
+                    quote = None;
+                }
+                current.push(c);
+            }
+            None if c == '"' || c == '\'' => {
+                quote = Some(c);
+                current.push(c);
+            }
+            None if c == ',' => parts.push(std::mem::take(&mut current)),
+            None => current.push(c),
+        }
+    }
+    if !current.trim().is_empty() || !parts.is_empty() {
+        parts.push(current);
+    }
+    parts
+        .into_iter()
+        .map(|arg| match parse_literal(&arg) {
+            Some(value) => value.to_string(),
+            None => arg.trim().to_string(),
+        })
+        .collect()
+}
+
+fn render_placeholder(
+    placeholder: &Placeholder<'_>,
+    props: &HashMap<String, Value>,
+    data: Option<&Value>,
+    escape: Escape,
+) -> Option<String> {
+    let mut value = lookup_path(placeholder.path, props, data)?.clone();
+    // Without an explicit escaping modifier, escape the leaf values first so
+    // structure added by later modifiers (`list`, `join`) is kept.
+    let explicit = placeholder
+        .modifiers
+        .iter()
+        .any(|m| Escape::from_modifier(&m.name).is_some());
+    if !explicit {
+        value = escape_value(value, escape);
+    }
+    for modifier in &placeholder.modifiers {
+        value = match Escape::from_modifier(&modifier.name) {
+            Some(mode) => escape_value(value, mode),
+            None => apply_modifier(value, modifier),
+        };
+    }
+    Some(value.to_string())
+}
+
+/// Resolve `name.key[prop].key`: plain segments are keys or indexes, and
+/// `[prop]` segments use the value of another prop as the key. A `$data`
+/// root looks into `data` unless a prop has that name.
+fn lookup_path<'v>(path: &str, props: &'v HashMap<String, Value>, data: Option<&'v Value>) -> Option<&'v Value> {
+    let mut segments: Vec<String> = Vec::new();
+    let mut rest = path;
+    while !rest.is_empty() {
+        if let Some(after) = rest.strip_prefix('[') {
+            let end = after.find(']')?;
+            segments.push(props.get(&after[..end])?.to_string());
+            rest = &after[end + 1..];
+        } else {
+            let end = rest.find(['.', '[']).unwrap_or(rest.len());
+            segments.push(rest[..end].to_string());
+            rest = &rest[end..];
+        }
+        if let Some(after) = rest.strip_prefix('.') {
+            rest = after;
+        }
+    }
+    let (name, keys) = segments.split_first()?;
+    let root = props.get(name).or_else(|| data.filter(|_| name == "$data"))?;
+    keys.iter().try_fold(root, |value, key| value.get(key))
+}
+
+/// Apply children modifiers in order. Unknown modifiers are ignored.
+fn render_children(children: &str, modifiers: &[Modifier]) -> String {
+    let mut children = children.to_string();
+    for modifier in modifiers {
+        children = match modifier.name.as_str() {
+            "inline" => escape_pipes(
+                &children
+                    .lines()
+                    .map(str::trim)
+                    .filter(|line| !line.is_empty())
+                    .collect::<Vec<_>>()
+                    .join(" "),
+            ),
+            "text" => plain_text(&children),
+            "quoted" => {
+                let prefix = modifier.args.first().map(String::as_str).unwrap_or("> ");
+                children
+                    .lines()
+                    .map(|line| {
+                        if line.is_empty() {
+                            prefix.trim_end().to_string()
+                        } else {
+                            format!("{prefix}{line}")
+                        }
+                    })
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            }
+            "indent" => {
+                let width = modifier.args.first().and_then(|n| n.parse().ok()).unwrap_or(2);
+                let pad = " ".repeat(width);
+                children
+                    .lines()
+                    .map(|line| if line.is_empty() { String::new() } else { format!("{pad}{line}") })
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            }
+            _ => children,
+        };
+    }
+    children
+}
+
+/// Strip Markdown syntax, keeping text and code. Blocks are separated by a
+/// blank line; line breaks inside a paragraph become spaces.
+fn plain_text(markdown: &str) -> String {
+    let mut blocks: Vec<String> = Vec::new();
+    let mut current = String::new();
+    for event in Parser::new(markdown) {
+        match event {
+            Event::Text(text) | Event::Code(text) => current.push_str(&text),
+            Event::SoftBreak | Event::HardBreak => current.push(' '),
+            Event::End(TagEnd::Paragraph | TagEnd::Heading(_) | TagEnd::Item | TagEnd::CodeBlock | TagEnd::TableCell) => {
+                let block = std::mem::take(&mut current);
+                if !block.trim().is_empty() {
+                    blocks.push(block.trim().to_string());
+                }
+            }
+            _ => {}
+        }
+    }
+    if !current.trim().is_empty() {
+        blocks.push(current.trim().to_string());
+    }
+    blocks.join("\n\n")
+}
+
+/// Escape every leaf of a value, turning the leaves into strings.
+fn escape_value(value: Value, escape: Escape) -> Value {
+    match value {
+        Value::Array(items) => Value::Array(items.into_iter().map(|v| escape_value(v, escape)).collect()),
+        Value::Object(entries) => Value::Object(
+            entries
+                .into_iter()
+                .map(|(k, v)| (k, escape_value(v, escape)))
+                .collect(),
+        ),
+        Value::Null => Value::Null,
+        other => Value::String(escape.apply(&other.to_string())),
+    }
+}
+
+/// Apply a value modifier. Unknown modifiers leave the value unchanged.
+fn apply_modifier(value: Value, modifier: &Modifier) -> Value {
+    match modifier.name.as_str() {
+        "join" => {
+            let sep = modifier.args.first().map(String::as_str).unwrap_or(", ");
+            match value {
+                Value::Array(items) => Value::String(
+                    items.iter().map(Value::to_string).collect::<Vec<_>>().join(sep),
+                ),
+                other => other,
+            }
+        }
+        "list" => {
+            let items = match value {
+                Value::Array(items) => items,
+                other => vec![other],
+            };
+            Value::String(
+                items
+                    .iter()
+                    .map(|item| format!("- {item}"))
+                    .collect::<Vec<_>>()
+                    .join("\n"),
+            )
+        }
+        "table" => table(value, &modifier.args),
+        _ => value,
+    }
+}
+
+/// A GFM table from an array of objects (one row each) or an object (one
+/// row per key, in a `key` column). Columns are the given names, or every
+/// key in order of first appearance.
+fn table(value: Value, columns: &[String]) -> Value {
+    let rows: Vec<Vec<(String, Value)>> = match value {
+        Value::Array(items) => items
+            .into_iter()
+            .map(|item| match item {
+                Value::Object(entries) => entries,
+                other => vec![("value".to_string(), other)],
+            })
+            .collect(),
+        Value::Object(entries) => entries
+            .into_iter()
+            .map(|(key, item)| {
+                let mut row = vec![("key".to_string(), Value::String(key))];
+                match item {
+                    Value::Object(entries) => row.extend(entries),
+                    other => row.push(("value".to_string(), other)),
+                }
+                row
+            })
+            .collect(),
+        other => return other,
+    };
+
+    let mut columns = columns.to_vec();
+    if columns.is_empty() {
+        for (key, _) in rows.iter().flatten() {
+            if !columns.contains(key) {
+                columns.push(key.clone());
+            }
+        }
+    }
+    if columns.is_empty() {
+        return Value::String(String::new());
+    }
+
+    let cell = |text: String| escape_pipes(&text.split_whitespace().collect::<Vec<_>>().join(" "));
+    let mut lines = vec![
+        format!("| {} |", columns.iter().map(|c| cell(c.clone())).collect::<Vec<_>>().join(" | ")),
+        format!("|{}", " --- |".repeat(columns.len())),
+    ];
+    for row in &rows {
+        let cells: Vec<String> = columns
+            .iter()
+            .map(|column| {
+                let value = row.iter().rev().find(|(key, _)| key == column).map(|(_, v)| v.to_string());
+                cell(value.unwrap_or_default())
+            })
+            .collect();
+        lines.push(format!("| {} |", cells.join(" | ")));
+    }
+    Value::String(lines.join("\n"))
+}
+
+/// Escape `|` for a table cell, leaving pipes the value already escaped.
+fn escape_pipes(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut backslashes = 0;
+    for c in text.chars() {
+        if c == '|' && backslashes % 2 == 0 {
+            out.push('\\');
+        }
+        backslashes = if c == '\\' { backslashes + 1 } else { 0 };
+        out.push(c);
+    }
+    out
+}
+
+/// Apply a block prefix (e.g. `> `) to every continuation line of `text`.
+/// Blank lines get the prefix without trailing whitespace.
+fn prefix_lines(text: &str, prefix: &str) -> String {
+    if prefix.is_empty() || !text.contains('\n') {
+        return text.to_string();
+    }
+    let mut expanded = String::new();
+    for (i, line) in text.lines().enumerate() {
+        if i > 0 {
+            expanded.push('\n');
+            if line.is_empty() {
+                expanded.push_str(prefix.trim_end());
+            } else {
+                expanded.push_str(prefix);
+                expanded.push_str(line);
+            }
+        } else {
+            expanded.push_str(line);
+        }
+    }
+    if text.ends_with('\n') {
+        expanded.push('\n');
+    }
+    expanded
+}
+
+/// Extract the repeatable block prefix from a line (e.g. `> ` from `> **warning**: text`).
+/// This captures leading `>`, spaces, and tabs that form the block structure.
+fn extract_block_prefix(line: &str) -> String {
+    let mut prefix = String::new();
+    for ch in line.chars() {
+        match ch {
+            '>' | ' ' | '\t' => prefix.push(ch),
+            _ => break,
+        }
+    }
+    prefix
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::value::parse_literal;
+
+    fn props(pairs: &[(&str, &str)]) -> HashMap<String, Value> {
+        pairs
+            .iter()
+            .map(|(k, v)| (k.to_string(), parse_literal(v).unwrap()))
+            .collect()
+    }
+
+    #[test]
+    fn test_array_prop_default_join() {
+        let p = props(&[("items", r#"["a", "b"]"#)]);
+        assert_eq!(apply_template("Items: {items}", &p, "", Escape::Markdown), "Items: a, b");
+    }
+
+    #[test]
+    fn test_join_and_list_modifiers() {
+        let p = props(&[("items", r#"["a", "b", "c"]"#)]);
+        assert_eq!(apply_template(r#"{items:join(" | ")}"#, &p, "", Escape::Markdown), "a | b | c");
+        assert_eq!(apply_template("{items:list}", &p, "", Escape::Markdown), "- a\n- b\n- c");
+    }
+
+    #[test]
+    fn test_path_lookup() {
+        let p = props(&[("meta", r#"{ title: "Hi", tags: ["x", "y"] }"#)]);
+        assert_eq!(apply_template("{meta.title} ({meta.tags.1})", &p, "", Escape::Markdown), "Hi (y)");
+    }
+
+    #[test]
+    fn test_unknown_placeholders_kept() {
+        let p = props(&[("a", "1")]);
+        assert_eq!(apply_template("{a} {b} { a } {}", &p, "", Escape::Markdown), "1 {b} { a } {}");
+    }
+
+    #[test]
+    fn test_values_not_rescanned() {
+        let p = props(&[("a", r#""{b}""#), ("b", "2")]);
+        assert_eq!(apply_template("{a}", &p, "kids", Escape::Markdown), "\\{b\\}");
+    }
+
+    #[test]
+    fn test_children_block_prefix() {
+        let result = apply_template("> {children}", &HashMap::new(), "one\n\ntwo", Escape::Markdown);
+        assert_eq!(result, "> one\n>\n> two");
+    }
+
+    #[test]
+    fn test_props_escaped_by_default() {
+        let p = props(&[("title", r##""# Admin](https://evil)""##), ("href", r#""javascript:alert(1)""#)]);
+        assert_eq!(
+            apply_template("[{title:link_text}]({href:url}) {title}", &p, "", Escape::Markdown),
+            "[\\# Admin\\](https://evil)]() \\# Admin\\](https://evil)"
+        );
+        assert_eq!(apply_template("{title:raw}", &p, "", Escape::Markdown), "# Admin](https://evil)");
+        assert_eq!(apply_template("{title}", &p, "", Escape::Raw), "# Admin](https://evil)");
+    }
+
+    #[test]
+    fn test_props_in_code_spans_not_escaped() {
+        let p = props(&[("name", r#""my_var|x""#)]);
+        assert_eq!(
+            apply_template("Use `{name}` or ``{name}`` not {name}", &p, "", Escape::Markdown),
+            "Use `my_var|x` or ``my_var|x`` not my\\_var\\|x"
+        );
+        assert_eq!(apply_template("A ` tick, then {name}", &p, "", Escape::Markdown), "A ` tick, then my\\_var\\|x");
+    }
+
+    #[test]
+    fn test_multiline_prop_keeps_block_prefix() {
+        let p = props(&[("note", r##""line one\n# not a heading""##)]);
+        assert_eq!(
+            apply_template("> **Note:** {note}", &p, "", Escape::Markdown),
+            "> **Note:** line one\n> \\# not a heading"
+        );
+    }
+
+    #[test]
+    fn test_every_children_placeholder() {
+        let result = apply_template("{children} / {children}", &HashMap::new(), "a", Escape::Markdown);
+        assert_eq!(result, "a / a");
+    }
+
+    #[test]
+    fn test_children_modifiers() {
+        let children = "Some **bold** and `code`.\n\n- one\n- two";
+        let none = HashMap::new();
+        assert_eq!(
+            apply_template("| {children:inline} |", &none, children, Escape::Markdown),
+            "| Some **bold** and `code`. - one - two |"
+        );
+        assert_eq!(
+            apply_template("{children:text:inline}", &none, children, Escape::Markdown),
+            "Some bold and code. one two"
+        );
+        assert_eq!(
+            apply_template("{children:quoted}", &none, "a\n\nb", Escape::Markdown),
+            "> a\n>\n> b"
+        );
+        assert_eq!(
+            apply_template("- Item\n{children:indent(4)}", &none, "a\n\nb", Escape::Markdown),
+            "- Item\n    a\n\n    b"
+        );
+    }
+
+    #[test]
+    fn test_children_inline_escapes_pipes() {
+        let none = HashMap::new();
+        assert_eq!(
+            apply_template("| {children:inline} |", &none, "a | b\n\n`x \\| y`", Escape::Markdown),
+            "| a \\| b `x \\| y` |"
+        );
+    }
+
+    #[test]
+    fn test_children_indent_counts_line_indentation() {
+        let none = HashMap::new();
+        assert_eq!(
+            apply_template("- Item\n  {children:indent(4)}", &none, "a\n\nb", Escape::Markdown),
+            "- Item\n    a\n\n    b"
+        );
+        assert_eq!(
+            apply_template("> {children:indent(2)}", &none, "a\nb", Escape::Markdown),
+            ">   a\n>   b"
+        );
+    }
+}
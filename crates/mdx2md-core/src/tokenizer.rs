@@ -1,3 +1,5 @@
+use std::ops::Range;
+
 #[derive(Debug, Clone, PartialEq)]
 pub enum Token {
     Frontmatter(String),
@@ -27,10 +29,28 @@ pub enum RawAttrValue {
     Expression(String),
 }
 
+/// Tags whose children are taken verbatim unless configured otherwise.
+pub const DEFAULT_RAW_TEXT_TAGS: &[&str] = &["pre", "script", "style", "CodeBlock", "Mermaid"];
+
 pub fn tokenize(input: &str) -> Result<Vec<Token>, TokenizeError> {
+    tokenize_with_raw_tags(input, DEFAULT_RAW_TEXT_TAGS)
+}
+
+/// Tokenize, capturing the content of each `raw_tags` element as a single
+/// `Markdown` token up to its matching close tag. Braces, tags and
+/// `import` lines inside are not parsed.
+pub fn tokenize_with_raw_tags<S: AsRef<str>>(input: &str, raw_tags: &[S]) -> Result<Vec<Token>, TokenizeError> {
+    Ok(tokenize_spanned(input, raw_tags)?.into_iter().map(|(token, _)| token).collect())
+}
+
+/// Like [`tokenize_with_raw_tags`], with the byte range of each token in
+/// `input`.
+pub fn tokenize_spanned<S: AsRef<str>>(input: &str, raw_tags: &[S]) -> Result<Vec<(Token, Range<usize>)>, TokenizeError> {
     let mut tokens = Vec::new();
     let mut chars: &str = input;
     let mut md_buf = String::new();
+    let mut md_start = 0;
+    let pos = |rest: &str| input.len() - rest.len();
 
     // Handle frontmatter at the very start
     if chars.starts_with("---\n") || chars.starts_with("---\r\n") {
@@ -38,17 +58,19 @@ pub fn tokenize(input: &str) -> Result<Vec<Token>, TokenizeError> {
         if let Some(end) = find_frontmatter_close(after_open) {
             let fm_content = &after_open[..end];
             let after_close = skip_past_newline(&after_open[end + 3..], 0);
-            tokens.push(Token::Frontmatter(fm_content.trim_end().to_string()));
+            tokens.push((Token::Frontmatter(fm_content.trim_end().to_string()), 0..pos(after_close)));
             chars = after_close;
         }
     }
 
     while !chars.is_empty() {
+        let start = pos(chars);
+
         // Check for import/export at line start
         if is_at_line_start(&md_buf) {
             if let Some((stmt, rest)) = try_parse_import_export(chars) {
-                flush_md(&mut md_buf, &mut tokens);
-                tokens.push(stmt);
+                flush_md(&mut md_buf, md_start..start, &mut tokens);
+                tokens.push((stmt, start..pos(rest)));
                 chars = rest;
                 continue;
             }
@@ -57,9 +79,35 @@ pub fn tokenize(input: &str) -> Result<Vec<Token>, TokenizeError> {
         // Check for JSX tag: `<ComponentName` or `</ComponentName`
         if chars.starts_with('<') {
             if let Some((tag_token, rest)) = try_parse_jsx_tag(chars) {
-                flush_md(&mut md_buf, &mut tokens);
-                tokens.push(tag_token);
+                flush_md(&mut md_buf, md_start..start, &mut tokens);
+                let raw_close = match &tag_token {
+                    Token::JsxOpenTag { tag, self_closing: false, .. } if raw_tags.iter().any(|t| t.as_ref() == tag) => {
+                        find_close_tag(rest, tag)
+                    }
+                    _ => None,
+                };
+                tokens.push((tag_token, start..pos(rest)));
                 chars = rest;
+                if let Some((content_end, close, after)) = raw_close {
+                    let content_start = pos(rest);
+                    if content_end > 0 {
+                        tokens.push((Token::Markdown(rest[..content_end].to_string()), content_start..content_start + content_end));
+                    }
+                    tokens.push((close, content_start + content_end..pos(after)));
+                    chars = after;
+                }
+                continue;
+            }
+        }
+
+        // A custom heading id (`## Install {#install}`) is Markdown, not an expression
+        if chars.starts_with("{#") {
+            if let Some(len) = heading_id_len(input, chars) {
+                if md_buf.is_empty() {
+                    md_start = start;
+                }
+                md_buf.push_str(&chars[..len]);
+                chars = &chars[len..];
                 continue;
             }
         }
@@ -67,8 +115,8 @@ pub fn tokenize(input: &str) -> Result<Vec<Token>, TokenizeError> {
         // Check for expression block `{...}`
         if chars.starts_with('{') {
             if let Some((expr, rest)) = try_parse_expression(chars) {
-                flush_md(&mut md_buf, &mut tokens);
-                tokens.push(expr);
+                flush_md(&mut md_buf, md_start..start, &mut tokens);
+                tokens.push((expr, start..pos(rest)));
                 chars = rest;
                 continue;
             }
@@ -76,11 +124,14 @@ pub fn tokenize(input: &str) -> Result<Vec<Token>, TokenizeError> {
 
         // Otherwise, consume one character as Markdown (safe for multi-byte UTF-8)
         let c = chars.chars().next().unwrap();
+        if md_buf.is_empty() {
+            md_start = start;
+        }
         md_buf.push(c);
         chars = &chars[c.len_utf8()..];
     }
 
-    flush_md(&mut md_buf, &mut tokens);
+    flush_md(&mut md_buf, md_start..input.len(), &mut tokens);
     Ok(tokens)
 }
 
@@ -97,9 +148,56 @@ impl std::fmt::Display for TokenizeError {
 
 impl std::error::Error for TokenizeError {}
 
-fn flush_md(buf: &mut String, tokens: &mut Vec<Token>) {
+fn flush_md(buf: &mut String, span: Range<usize>, tokens: &mut Vec<(Token, Range<usize>)>) {
     if !buf.is_empty() {
-        tokens.push(Token::Markdown(std::mem::take(buf)));
+        tokens.push((Token::Markdown(std::mem::take(buf)), span));
+    }
+}
+
+/// Length of a `{#custom-id}` at `rest` if it ends a heading: an ATX line
+/// (also inside block quotes and list items) or the text of a Setext heading.
+fn heading_id_len(input: &str, rest: &str) -> Option<usize> {
+    let end = rest.find('}')?;
+    let id = &rest[2..end];
+    if id.is_empty() || !id.chars().all(|c| c.is_alphanumeric() || matches!(c, '-' | '_' | ':' | '.')) {
+        return None;
+    }
+    let line_end = rest.find('\n').unwrap_or(rest.len());
+    if !rest[end + 1..line_end].trim().is_empty() {
+        return None;
+    }
+    let pos = input.len() - rest.len();
+    let line = strip_container_markers(&input[input[..pos].rfind('\n').map_or(0, |i| i + 1)..pos]);
+    let hashes = line.len() - line.trim_start_matches('#').len();
+    if (1..=6).contains(&hashes) && line[hashes..].starts_with([' ', '\t']) {
+        return Some(end + 1);
+    }
+    // Setext: the next line is a `===` or `---` underline
+    let next = rest[line_end..].strip_prefix('\n')?;
+    let underline = next.lines().next()?.trim_start_matches(['>', ' ', '\t']).trim_end();
+    let setext = !underline.is_empty()
+        && (underline.chars().all(|c| c == '=') || underline.chars().all(|c| c == '-'));
+    (setext && !line.trim().is_empty()).then_some(end + 1)
+}
+
+/// A line without its leading block quote and list item markers.
+fn strip_container_markers(mut line: &str) -> &str {
+    loop {
+        line = line.trim_start_matches([' ', '\t']);
+        if let Some(rest) = line.strip_prefix('>') {
+            line = rest;
+            continue;
+        }
+        let digits = line.len() - line.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+        let marker = match line[digits..].chars().next() {
+            Some('-' | '*' | '+') if digits == 0 => 1,
+            Some('.' | ')') if (1..=9).contains(&digits) => digits + 1,
+            _ => return line,
+        };
+        if !line[marker..].starts_with([' ', '\t']) {
+            return line;
+        }
+        line = &line[marker..];
     }
 }
 
@@ -141,8 +239,8 @@ fn try_parse_import_export(s: &str) -> Option<(Token, &str)> {
     }
 
     // Peek ahead to see if this looks like a JS import/export (not an HTML tag or MD)
-    let keyword_len = if is_import { 7 } else { 7 };
-    let rest_after_keyword = &s[keyword_len..];
+    // Both keywords are 6 characters plus the trailing space
+    let rest_after_keyword = &s[7..];
 
     // `export default` is also an export
     // For imports: `import X from`, `import { X } from`, `import "x"`
@@ -181,7 +279,7 @@ fn try_parse_import_export(s: &str) -> Option<(Token, &str)> {
                     if depth == 0 {
                         // Check if next non-whitespace is a newline or semicolon
                         let after = &s[i + 1..];
-                        let trimmed = after.trim_start_matches(|c: char| c == ' ' || c == '\t');
+                        let trimmed = after.trim_start_matches([' ', '\t']);
                         if trimmed.starts_with('\n') || trimmed.starts_with('\r') || trimmed.starts_with(';') || trimmed.is_empty() {
                             let end = if trimmed.starts_with(';') {
                                 s.len() - trimmed.len() + 1
@@ -240,13 +338,9 @@ fn try_parse_import_export(s: &str) -> Option<(Token, &str)> {
 }
 
 fn consume_newline(s: &str) -> &str {
-    if s.starts_with("\r\n") {
-        &s[2..]
-    } else if s.starts_with('\n') {
-        &s[1..]
-    } else {
-        s
-    }
+    s.strip_prefix("\r\n")
+        .or_else(|| s.strip_prefix('\n'))
+        .unwrap_or(s)
 }
 
 /// Try to parse a JSX/HTML tag starting with `<`.
@@ -383,6 +477,23 @@ fn try_parse_jsx_tag(s: &str) -> Option<(Token, &str)> {
     }
 }
 
+/// Find the first `</tag>` in `s`. Returns the content length, the close
+/// token and the input after it.
+fn find_close_tag<'a>(s: &'a str, tag: &str) -> Option<(usize, Token, &'a str)> {
+    let needle = format!("</{tag}");
+    let mut from = 0;
+    while let Some(i) = s[from..].find(&needle) {
+        let start = from + i;
+        if let Some((close @ Token::JsxCloseTag { .. }, rest)) = try_parse_jsx_tag(&s[start..]) {
+            if matches!(&close, Token::JsxCloseTag { tag: t } if t == tag) {
+                return Some((start, close, rest));
+            }
+        }
+        from = start + needle.len();
+    }
+    None
+}
+
 /// Parse a `{...}` expression, tracking brace depth.
 /// Empty braces `{}` are not treated as expressions (likely literal code).
 fn try_parse_expression(s: &str) -> Option<(Token, &str)> {
@@ -597,6 +708,38 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_raw_text_tags() {
+        let input = "<CodeBlock language=\"ts\">\nimport { a } from 'b';\nconst m: Map<string, {x: number}> = new Map();\n</CodeBlock>\n{expr}";
+        let tokens = tokenize(input).unwrap();
+        assert_eq!(tokens.len(), 5);
+        assert_eq!(
+            tokens[1],
+            Token::Markdown("\nimport { a } from 'b';\nconst m: Map<string, {x: number}> = new Map();\n".to_string())
+        );
+        assert_eq!(tokens[2], Token::JsxCloseTag { tag: "CodeBlock".to_string() });
+        assert_eq!(tokens[4], Token::Expression("expr".to_string()));
+
+        // Not raw unless listed
+        let tokens = tokenize_with_raw_tags("<Foo>{x}</Foo>", &["CodeBlock"]).unwrap();
+        assert_eq!(tokens[1], Token::Expression("x".to_string()));
+        let tokens = tokenize_with_raw_tags("<Foo>{x}</Foo>", &["Foo"]).unwrap();
+        assert_eq!(tokens[1], Token::Markdown("{x}".to_string()));
+    }
+
+    #[test]
+    fn test_heading_ids() {
+        let tokens = tokenize("## Install {#install}\n> ### Quoted {#q}\n- ## In list {#inlist}\n\nSetext {#st}\n---\n\nText {#x}\n").unwrap();
+        assert_eq!(
+            tokens,
+            vec![
+                Token::Markdown("## Install {#install}\n> ### Quoted {#q}\n- ## In list {#inlist}\n\nSetext {#st}\n---\n\nText ".to_string()),
+                Token::Expression("#x".to_string()),
+                Token::Markdown("\n".to_string()),
+            ]
+        );
+    }
+
     #[test]
     fn test_kitchen_sink_token_types() {
         let input = r#"---
@@ -1,68 +1,353 @@
 use crate::ast::*;
 use crate::config::*;
-use std::collections::HashMap;
+use crate::diagnostic::Diagnostic;
+use crate::escape::escape_text;
+use crate::eval::{evaluate, identifier_paths, to_jsx_text};
+use crate::html;
+use crate::matcher::{ComponentMatcher, PatternError};
+use crate::resolver::{ElementResolver, LegacyResolver, NoResolver, ResolveContext};
+use crate::rewriter::TOC_MARKER;
+use crate::scope::{document_scope, lookup};
+use crate::serialize::{html_open_tag, to_mdx};
+use crate::targets::TargetFilter;
+use crate::template::{apply_template, apply_template_with_data};
+use crate::value::Value;
+use crate::whitespace::{dedent_children, normalize_whitespace, trim_trailing_indent};
+use std::cell::{OnceCell, RefCell};
+use std::collections::{BTreeMap, HashMap};
 
-/// External resolver for JSX components. Called with (tag, props_map, children_str)
+/// External resolver for JSX components. Called with (tag, props_map, children_str, context)
 /// and returns Some(rendered_string) to handle the component, or None to fall back
-/// to config-based templates.
+/// to config-based templates. Props are typed: literal expressions arrive as
+/// arrays, numbers, booleans, etc. (see [`Value`]).
+///
+/// See [`ElementResolver`] for resolvers that need the children AST, lazy
+/// rendering or diagnostics.
 pub trait ComponentResolver {
-    fn resolve(&self, tag: &str, props: &HashMap<String, String>, children: &str) -> Option<String>;
+    fn resolve(
+        &self,
+        tag: &str,
+        props: &HashMap<String, Value>,
+        children: &str,
+        context: &RenderContext,
+    ) -> Option<String>;
+}
+
+/// Where a JSX element sits in the document.
+///
+/// Templates see these as `{$parent}`, `{$ancestors}`, `{$depth}`, `{$index}`,
+/// `{$position}` (1-based index) and `{$count}`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RenderContext {
+    /// Tags of the enclosing JSX elements, outermost first.
+    pub ancestors: Vec<String>,
+    /// 0-based index among sibling JSX elements with the same tag.
+    pub index: usize,
+    /// Number of sibling JSX elements with the same tag, including this one.
+    pub sibling_count: usize,
 }
 
-/// No-op resolver that always falls back to config.
-struct NoResolver;
-impl ComponentResolver for NoResolver {
-    fn resolve(&self, _tag: &str, _props: &HashMap<String, String>, _children: &str) -> Option<String> {
-        None
+impl RenderContext {
+    /// Tag of the enclosing JSX element, if any.
+    pub fn parent(&self) -> Option<&str> {
+        self.ancestors.last().map(String::as_str)
+    }
+
+    /// Nesting depth: 0 for top-level elements.
+    pub fn depth(&self) -> usize {
+        self.ancestors.len()
+    }
+
+    /// Template variables for this context, keyed with a `$` prefix so they
+    /// never collide with props.
+    fn template_vars(&self) -> [(&'static str, Value); 6] {
+        [
+            ("$parent", self.parent().map_or(Value::Null, |p| Value::String(p.to_string()))),
+            (
+                "$ancestors",
+                Value::Array(self.ancestors.iter().cloned().map(Value::String).collect()),
+            ),
+            ("$depth", Value::Number(self.depth() as f64)),
+            ("$index", Value::Number(self.index as f64)),
+            ("$position", Value::Number((self.index + 1) as f64)),
+            ("$count", Value::Number(self.sibling_count as f64)),
+        ]
     }
 }
 
 /// Layer 1: Transform an MDX AST into raw Markdown by resolving JSX components,
 /// stripping imports/exports, and handling expressions according to config.
 pub fn transform(doc: &MdxDocument, config: &Config) -> String {
-    transform_with_resolver(doc, config, &NoResolver)
+    transform_with_element_resolver(doc, config, &NoResolver).0
 }
 
 /// Layer 1 with an external component resolver (used by WASM for JS callbacks).
 pub fn transform_with_resolver(doc: &MdxDocument, config: &Config, resolver: &dyn ComponentResolver) -> String {
+    transform_with_element_resolver(doc, config, &LegacyResolver(resolver)).0
+}
+
+/// Layer 1 with an [`ElementResolver`], returning the Markdown together with
+/// any diagnostics reported during rendering.
+///
+/// A config with an invalid component or filter pattern renders nothing and
+/// reports an error, rather than converting without those rules.
+pub fn transform_with_element_resolver(
+    doc: &MdxDocument,
+    config: &Config,
+    resolver: &dyn ElementResolver,
+) -> (String, Vec<Diagnostic>) {
+    match Rules::new(config) {
+        Ok(rules) => render_document(doc, config, &rules, resolver),
+        Err(e) => (String::new(), vec![Diagnostic::error(None, e.message)]),
+    }
+}
+
+/// Layer 1 with rules compiled by the caller.
+pub(crate) fn render_document(
+    doc: &MdxDocument,
+    config: &Config,
+    rules: &Rules,
+    resolver: &dyn ElementResolver,
+) -> (String, Vec<Diagnostic>) {
+    let mut renderer = Renderer::new(config, rules, resolver);
+    renderer.source = &doc.source;
+    if !rules.filter.keeps_document(doc) {
+        return (String::new(), Vec::new());
+    }
+    if config.options.expression_handling.uses_scope() {
+        renderer.scope = document_scope(doc, config);
+    }
     let mut output = String::new();
 
-    for node in &doc.nodes {
-        transform_node(node, config, resolver, &mut output);
+    for (node, context) in doc.nodes.iter().zip(sibling_contexts(&doc.nodes, &[])) {
+        renderer.transform_node(node, &context, &mut output);
     }
 
-    clean_blank_lines(&output)
+    (clean_blank_lines(&output, &renderer.verbatim.take()), renderer.take_diagnostics())
 }
 
-fn transform_node(node: &MdxNode, config: &Config, resolver: &dyn ComponentResolver, out: &mut String) {
-    match node {
-        MdxNode::Frontmatter(content) => {
-            if config.options.preserve_frontmatter {
-                out.push_str("---\n");
-                out.push_str(content);
-                out.push_str("\n---\n");
-            }
+/// Component and filter patterns compiled from a config, once per
+/// conversion.
+pub(crate) struct Rules<'a> {
+    components: ComponentMatcher<&'a ComponentTransform>,
+    /// Rules merged from presets, below every rule of the config itself.
+    presets: ComponentMatcher<&'a ComponentTransform>,
+    filter: TargetFilter<'a>,
+}
+
+impl<'a> Rules<'a> {
+    pub(crate) fn new(config: &'a Config) -> Result<Self, PatternError> {
+        Ok(Self {
+            components: ComponentMatcher::from_components(&config.components)?,
+            presets: ComponentMatcher::from_presets(&config.components)?,
+            filter: TargetFilter::new(config)?,
+        })
+    }
+
+    /// The rule for a tag, without the `_default` fallback.
+    fn find_pattern(&self, tag: &str) -> Option<&'a ComponentTransform> {
+        self.components
+            .find_pattern(tag)
+            .or_else(|| self.presets.find_pattern(tag))
+            .copied()
+    }
+
+    /// The rule for a tag, falling back to `_default`.
+    fn find(&self, tag: &str) -> Option<&'a ComponentTransform> {
+        self.find_pattern(tag)
+            .or_else(|| self.components.find(tag).or_else(|| self.presets.find(tag)).copied())
+    }
+}
+
+/// Per-document rendering state shared by all nodes.
+pub(crate) struct Renderer<'a> {
+    pub(crate) config: &'a Config,
+    pub(crate) rules: &'a Rules<'a>,
+    pub(crate) resolver: &'a dyn ElementResolver,
+    /// Variables for `expression_handling = "resolve"`.
+    pub(crate) scope: Value,
+    /// Source of the document, for `mode = "raw"`.
+    pub(crate) source: &'a str,
+    diagnostics: RefCell<Vec<Diagnostic>>,
+    /// `[data]` as seen by `{$data...}`, built on first use.
+    data: OnceCell<Value>,
+    /// Content of raw-text elements, kept out of whitespace normalization.
+    pub(crate) verbatim: RefCell<Vec<String>>,
+}
+
+impl<'a> Renderer<'a> {
+    pub(crate) fn new(config: &'a Config, rules: &'a Rules<'a>, resolver: &'a dyn ElementResolver) -> Self {
+        Self {
+            config,
+            rules,
+            resolver,
+            scope: Value::Object(Vec::new()),
+            source: "",
+            diagnostics: RefCell::new(Vec::new()),
+            data: OnceCell::new(),
+            verbatim: RefCell::new(Vec::new()),
         }
-        MdxNode::Import(_) => {
-            if !config.options.strip_imports {
-                if let MdxNode::Import(s) = node {
+    }
+
+    pub(crate) fn report(&self, diagnostic: Diagnostic) {
+        self.diagnostics.borrow_mut().push(diagnostic);
+    }
+
+    pub(crate) fn take_diagnostics(&self) -> Vec<Diagnostic> {
+        self.diagnostics.take()
+    }
+
+    fn transform_node(&self, node: &MdxNode, context: &RenderContext, out: &mut String) {
+        let config = self.config;
+        match node {
+            MdxNode::Frontmatter(content) => {
+                if config.options.preserve_frontmatter {
+                    out.push_str("---\n");
+                    out.push_str(content);
+                    out.push_str("\n---\n");
+                }
+            }
+            MdxNode::Import(s) => {
+                if !config.options.strip_imports {
                     out.push_str(s);
                     out.push('\n');
                 }
             }
-        }
-        MdxNode::Export(_) => {
-            if !config.options.strip_exports {
-                if let MdxNode::Export(s) = node {
+            MdxNode::Export(s) => {
+                if !config.options.strip_exports {
                     out.push_str(s);
                     out.push('\n');
                 }
             }
+            MdxNode::Markdown(content) => {
+                out.push_str(content);
+            }
+            MdxNode::Expression(content) => self.render_text_expression(content, out),
+            MdxNode::JsxElement {
+                tag,
+                attributes,
+                children,
+                self_closing,
+                ..
+            } => {
+                let element = JsxElement {
+                    tag,
+                    attributes,
+                    children,
+                    self_closing: *self_closing,
+                };
+                if !self.rules.filter.keeps_element(tag, |name| element.prop(name)) {
+                    return;
+                }
+
+                // Filled in by `[markdown.toc]` in Layer 2, unless a rule other
+                // than `_default` renders the tag
+                if config.markdown.toc.is_some()
+                    && matches!(tag.as_str(), "TOC" | "TOCInline")
+                    && self.rules.find_pattern(tag).is_none()
+                {
+                    out.push_str(TOC_MARKER);
+                    return;
+                }
+
+                // Expression handling and the rule's prop cleanup apply to
+                // every way of rendering the element, resolvers included
+                let rule = self.rules.find_pattern(tag);
+                let props = self.expression_props(element.props());
+                let (props, missing) = match rule.or_else(|| self.rules.find(tag)) {
+                    Some(ct) => ct.prepare_props(props),
+                    None => (props, Vec::new()),
+                };
+                let cx = ResolveContext {
+                    renderer: self,
+                    element,
+                    props,
+                    context,
+                    children: OnceCell::new(),
+                    rendered: RefCell::new(Vec::new()),
+                };
+                if !missing.is_empty() {
+                    for name in missing {
+                        self.report(Diagnostic::error(Some(tag), format!("missing required prop `{name}`")));
+                    }
+                    out.push_str(&cx.render_children());
+                    return;
+                }
+
+                // Try external resolver first, then config templates
+                match self.resolver.resolve(&element, &cx) {
+                    Ok(Some(rendered)) => {
+                        out.push_str(&rendered);
+                        return;
+                    }
+                    Ok(None) => {}
+                    Err(e) => self.report(Diagnostic::error(Some(tag), e.message)),
+                }
+
+                // Component rules win over the built-in HTML conversion, which
+                // wins over `_default`
+                if rule.is_none() && config.options.html_to_markdown {
+                    if let Some(rendered) = html::render(&element, &cx) {
+                        out.push_str(&rendered);
+                        return;
+                    }
+                }
+
+                match rule.or_else(|| self.rules.find(tag)) {
+                    Some(ct) => match ct.mode {
+                        ComponentMode::Template => {
+                            let mut vars = cx.props.clone();
+                            for (name, value) in context.template_vars() {
+                                vars.insert(name.to_string(), value);
+                            }
+                            let data = ct.template.contains("$data").then(|| self.data.get_or_init(|| config.data_value()));
+                            out.push_str(&apply_template_with_data(
+                                &ct.template,
+                                &vars,
+                                data,
+                                &cx.render_children(),
+                                config.options.template_escape,
+                            ));
+                        }
+                        ComponentMode::Drop => {}
+                        ComponentMode::Unwrap => out.push_str(&cx.render_children()),
+                        ComponentMode::Html => {
+                            out.push_str(&html_open_tag(tag, &ordered_props(attributes, &ct.rename, &cx.props), *self_closing));
+                            if !*self_closing {
+                                // Block content needs blank lines so Markdown inside
+                                // the HTML element is still rendered.
+                                let children_str = cx.render_children();
+                                let sep = if children_str.contains('\n') { "\n\n" } else { "" };
+                                out.push_str(sep);
+                                out.push_str(&children_str);
+                                out.push_str(sep);
+                                out.push_str(&format!("</{tag}>"));
+                            }
+                        }
+                        ComponentMode::Raw => match self.apply_identifier_rules(node) {
+                            Some(rewritten) => out.push_str(&to_mdx(&rewritten, self.source)),
+                            None => out.push_str(&to_mdx(node, self.source)),
+                        },
+                    },
+                    None => {
+                        out.push_str(&cx.render_children());
+                    }
+                }
+            }
         }
-        MdxNode::Markdown(content) => {
-            out.push_str(content);
-        }
-        MdxNode::Expression(content) => match config.options.expression_handling {
+    }
+
+    /// Render a text expression with the mode picked by `[expressions]`
+    /// identifier rules, or `expression_handling`.
+    fn render_text_expression(&self, content: &str, out: &mut String) {
+        let handling = self
+            .expression_rule(content)
+            .unwrap_or(&self.config.options.expression_handling);
+        self.render_expression(content, handling, out);
+    }
+
+    fn render_expression(&self, content: &str, handling: &ExpressionHandling, out: &mut String) {
+        match handling {
             ExpressionHandling::Strip => {}
             ExpressionHandling::PreserveRaw => {
                 out.push('{');
@@ -70,192 +355,276 @@ fn transform_node(node: &MdxNode, config: &Config, resolver: &dyn ComponentResol
                 out.push('}');
             }
             ExpressionHandling::Placeholder => {
-                out.push_str("[expression]");
+                out.push_str(&self.placeholder(content));
             }
-        },
-        MdxNode::JsxElement {
-            tag,
-            attributes,
-            children,
-            ..
-        } => {
-            let children_str = transform_children(children, config, resolver);
-
-            // Build props map for resolver
-            let props: HashMap<String, String> = attributes
-                .iter()
-                .map(|a| {
-                    let val = match &a.value {
-                        Some(AttrValue::String(s)) => s.clone(),
-                        Some(AttrValue::Expression(e)) => e.clone(),
-                        None => "true".to_string(),
-                    };
-                    (a.name.clone(), val)
-                })
-                .collect();
-
-            // Try external resolver first, then config templates
-            if let Some(rendered) = resolver.resolve(tag, &props, &children_str) {
-                out.push_str(&rendered);
-            } else {
-                let component_config = config
-                    .components
-                    .get(tag)
-                    .or_else(|| config.components.get("_default"));
-
-                match component_config {
-                    Some(ct) => {
-                        let rendered = apply_template(&ct.template, attributes, &children_str);
-                        out.push_str(&rendered);
+            ExpressionHandling::Resolve | ExpressionHandling::Evaluate => {
+                let rendered = if *handling == ExpressionHandling::Resolve {
+                    lookup(&self.scope, content).map(Value::to_string)
+                } else {
+                    evaluate(content, &self.scope).map(|value| to_jsx_text(&value))
+                };
+                match rendered {
+                    // Values are text, not Markdown
+                    Some(text) => {
+                        let line = &out[out.rfind('\n').map_or(0, |i| i + 1)..];
+                        out.push_str(&escape_text(&text, line.trim().is_empty()));
                     }
                     None => {
-                        out.push_str(&children_str);
+                        let fallback = &self.config.options.unresolved_expressions;
+                        if !fallback.uses_scope() {
+                            self.render_expression(content, fallback, out);
+                        }
                     }
                 }
             }
         }
     }
-}
 
-fn transform_children(children: &[MdxNode], config: &Config, resolver: &dyn ComponentResolver) -> String {
-    let mut parts: Vec<String> = Vec::new();
-    for child in children {
-        let mut buf = String::new();
-        transform_node(child, config, resolver, &mut buf);
-        parts.push(buf);
-    }
-
-    // Trim trailing whitespace from markdown segments that precede other nodes.
-    // This prevents indentation leaking from MDX source formatting.
-    let mut out = String::new();
-    for (i, part) in parts.iter().enumerate() {
-        if i < parts.len() - 1 {
-            // Trim trailing whitespace (spaces/tabs) from lines, but keep newlines
-            out.push_str(&trim_trailing_line_spaces(part));
-        } else {
-            out.push_str(part);
-        }
+    /// The `[expressions]` placeholder with `{expr}` set to the source.
+    fn placeholder(&self, content: &str) -> String {
+        let vars = HashMap::from([("expr".to_string(), Value::String(content.trim().to_string()))]);
+        apply_template(&self.config.expressions.placeholder, &vars, "", self.config.options.template_escape)
     }
 
-    out.trim().to_string()
-}
-
-/// Trim trailing spaces/tabs from each line (but preserve newlines).
-fn trim_trailing_line_spaces(s: &str) -> String {
-    s.lines()
-        .map(|l| l.trim_end())
-        .collect::<Vec<_>>()
-        .join("\n")
-        + if s.ends_with('\n') { "\n" } else { "" }
-}
-
-/// Replace `{attr_name}` placeholders in a template with attribute values,
-/// and `{children}` with the rendered children string.
-///
-/// When `{children}` expands to multiple lines and the template line has a
-/// prefix before `{children}` (e.g. `> `), that prefix is applied to all
-/// continuation lines of the expanded children.
-fn apply_template(template: &str, attributes: &[Attribute], children: &str) -> String {
-    let mut result = template.to_string();
+    /// The most restrictive identifier rule matching a variable the
+    /// expression references.
+    fn expression_rule(&self, content: &str) -> Option<&ExpressionHandling> {
+        let rules = &self.config.expressions.identifiers;
+        if rules.is_empty() {
+            return None;
+        }
+        let covers = |path: &str, name: &str| {
+            path.strip_prefix(name)
+                .is_some_and(|rest| rest.is_empty() || rest.starts_with('.'))
+        };
+        identifier_paths(content)
+            .iter()
+            .flat_map(|path| {
+                rules.iter().filter(move |(name, _)| match path.strip_suffix(".*") {
+                    // A computed key (`process[key]`) could reach any property
+                    Some(prefix) => covers(prefix, name) || covers(name, prefix),
+                    None => covers(path, name),
+                })
+            })
+            .map(|(_, handling)| handling)
+            .min_by_key(|handling| handling.restrictiveness())
+    }
 
-    // Handle literal \n in templates (from TOML strings)
-    result = result.replace("\\n", "\n");
+    /// `node` with `[expressions]` identifier rules applied to the
+    /// expressions in it, for `mode = "raw"`. `None` when no rule changes
+    /// anything, so the source can be copied as written.
+    fn apply_identifier_rules(&self, node: &MdxNode) -> Option<MdxNode> {
+        let rule = |src: &str| self.expression_rule(src).filter(|h| **h != ExpressionHandling::PreserveRaw);
+        match node {
+            MdxNode::Expression(src) => {
+                let mut text = String::new();
+                self.render_expression(src, rule(src)?, &mut text);
+                Some(MdxNode::Markdown(text))
+            }
+            MdxNode::JsxElement {
+                tag,
+                attributes,
+                children,
+                self_closing,
+                ..
+            } => {
+                let mut changed = false;
+                let attributes = attributes
+                    .iter()
+                    .filter_map(|attr| {
+                        let Some(AttrValue::Expression(src)) = &attr.value else {
+                            return Some(attr.clone());
+                        };
+                        let Some(handling) = rule(src) else {
+                            return Some(attr.clone());
+                        };
+                        changed = true;
+                        let value = match self.expression_value(src, handling)? {
+                            Value::Expression(src) => AttrValue::Expression(src),
+                            value => AttrValue::String(value.to_string()),
+                        };
+                        Some(Attribute {
+                            name: attr.name.clone(),
+                            value: Some(value),
+                        })
+                    })
+                    .collect();
+                let children = children
+                    .iter()
+                    .map(|child| match self.apply_identifier_rules(child) {
+                        Some(rewritten) => {
+                            changed = true;
+                            rewritten
+                        }
+                        None => child.clone(),
+                    })
+                    .collect();
+                changed.then(|| MdxNode::JsxElement {
+                    tag: tag.clone(),
+                    attributes,
+                    children,
+                    self_closing: *self_closing,
+                    span: None,
+                })
+            }
+            _ => None,
+        }
+    }
 
-    // Replace attribute placeholders first
-    for attr in attributes {
-        let placeholder = format!("{{{}}}", attr.name);
-        let value = match &attr.value {
-            Some(AttrValue::String(s)) => s.clone(),
-            Some(AttrValue::Expression(e)) => e.clone(),
-            None => "true".to_string(),
+    /// Apply expression handling to expression props (`href={url}`): by
+    /// `expressions.attributes` if set, otherwise resolving them in
+    /// `resolve`/`evaluate` mode and keeping them as written in the others.
+    /// Stripped props are removed.
+    fn expression_props(&self, props: HashMap<String, Value>) -> HashMap<String, Value> {
+        let options = &self.config.options;
+        let default = match &self.config.expressions.attributes {
+            Some(handling) => handling,
+            None if options.expression_handling.uses_scope() => &options.expression_handling,
+            None => &ExpressionHandling::PreserveRaw,
         };
-        result = result.replace(&placeholder, &value);
-    }
-
-    // Replace {children} with line-prefix awareness
-    if let Some(placeholder_pos) = result.find("{children}") {
-        let before = &result[..placeholder_pos];
-        let after = &result[placeholder_pos + "{children}".len()..];
-
-        // Determine the block prefix: leading `> ` or whitespace from the line containing {children}
-        let line_start = before.rfind('\n').map(|p| p + 1).unwrap_or(0);
-        let line_content = &before[line_start..];
-        let prefix = extract_block_prefix(line_content);
-        let is_block_prefix = !prefix.is_empty();
-
-        if is_block_prefix && children.contains('\n') {
-            let child_lines: Vec<&str> = children.lines().collect();
-            let mut expanded = String::new();
-            for (i, line) in child_lines.iter().enumerate() {
-                if i == 0 {
-                    expanded.push_str(line);
+        props
+            .into_iter()
+            .filter_map(|(name, value)| match value.as_expression() {
+                Some(src) => {
+                    let handling = self.expression_rule(src).unwrap_or(default);
+                    self.expression_value(src, handling).map(|value| (name, value))
+                }
+                None => Some((name, value)),
+            })
+            .collect()
+    }
+
+    fn expression_value(&self, src: &str, handling: &ExpressionHandling) -> Option<Value> {
+        match handling {
+            ExpressionHandling::Strip => None,
+            ExpressionHandling::PreserveRaw => Some(Value::Expression(src.to_string())),
+            ExpressionHandling::Placeholder => Some(Value::String(self.placeholder(src))),
+            ExpressionHandling::Resolve | ExpressionHandling::Evaluate => {
+                let value = if *handling == ExpressionHandling::Resolve {
+                    lookup(&self.scope, src).cloned()
                 } else {
-                    expanded.push('\n');
-                    if line.is_empty() {
-                        // Blank line inside blockquote: just the prefix marker
-                        expanded.push_str(prefix.trim_end());
-                    } else {
-                        expanded.push_str(&prefix);
-                        expanded.push_str(line);
-                    }
+                    evaluate(src, &self.scope)
+                };
+                let fallback = &self.config.options.unresolved_expressions;
+                match value {
+                    Some(value) => Some(value),
+                    None if !fallback.uses_scope() => self.expression_value(src, fallback),
+                    None => None,
                 }
             }
-            // Handle trailing newline in children
-            if children.ends_with('\n') {
-                expanded.push('\n');
-            }
-            result = format!("{before}{expanded}{after}");
-        } else {
-            result = format!("{before}{children}{after}");
         }
     }
 
-    result
-}
+    pub(crate) fn transform_children(&self, children: &[MdxNode], ancestors: &[String]) -> String {
+        let mut parts: Vec<String> = Vec::new();
+        let dedented = dedent_children(children);
 
-/// Extract the repeatable block prefix from a line (e.g. `> ` from `> **warning**: text`).
-/// This captures leading `>`, spaces, and tabs that form the block structure.
-fn extract_block_prefix(line: &str) -> String {
-    let mut prefix = String::new();
-    for ch in line.chars() {
-        match ch {
-            '>' | ' ' | '\t' => prefix.push(ch),
-            _ => break,
+        // Raw-text elements keep their content as written, apart from the
+        // source indentation and surrounding blank lines
+        let raw_parent = ancestors.last().is_some_and(|tag| self.config.options.raw_text_tags.contains(tag));
+        if let ([MdxNode::Markdown(_)], [Some(text)], true) = (children, dedented.as_slice(), raw_parent) {
+            let text = text.trim_start_matches(['\n', '\r']).trim_end().to_string();
+            self.verbatim.borrow_mut().push(text.clone());
+            return text;
+        }
+        for ((child, context), text) in children.iter().zip(sibling_contexts(children, ancestors)).zip(dedented) {
+            // Markdown children have their source indentation removed so they
+            // don't become indented code blocks once unwrapped.
+            let buf = match text {
+                Some(text) => text,
+                None => {
+                    let mut buf = String::new();
+                    self.transform_node(child, &context, &mut buf);
+                    buf
+                }
+            };
+            parts.push(buf);
         }
+
+        // Drop indentation before other nodes so it doesn't leak into the
+        // output. Whitespace is normalized once for the whole document.
+        let last = parts.len().saturating_sub(1);
+        let mut out = String::new();
+        for (i, (part, child)) in parts.iter().zip(children).enumerate() {
+            if i < last && matches!(child, MdxNode::Markdown(_)) {
+                out.push_str(trim_trailing_indent(part));
+            } else {
+                out.push_str(part);
+            }
+        }
+
+        out.trim().to_string()
     }
-    prefix
 }
 
-/// Collapse runs of 3+ blank lines into 2 (one blank line between blocks).
-fn clean_blank_lines(input: &str) -> String {
-    let mut result = String::with_capacity(input.len());
-    let mut consecutive_newlines = 0u32;
+/// Prepared props in source order, following renames, then the ones only
+/// set by `defaults` by name.
+fn ordered_props(attributes: &[Attribute], rename: &BTreeMap<String, String>, props: &HashMap<String, Value>) -> Vec<(String, Value)> {
+    let mut ordered: Vec<(String, Value)> = Vec::with_capacity(props.len());
+    for attr in attributes {
+        let name = rename.get(&attr.name).unwrap_or(&attr.name);
+        if let Some(value) = props.get(name).filter(|_| ordered.iter().all(|(n, _)| n != name)) {
+            ordered.push((name.clone(), value.clone()));
+        }
+    }
+    let mut rest: Vec<(String, Value)> = props
+        .iter()
+        .filter(|(name, _)| ordered.iter().all(|(n, _)| n != *name))
+        .map(|(name, value)| (name.clone(), value.clone()))
+        .collect();
+    rest.sort_by(|a, b| a.0.cmp(&b.0));
+    ordered.extend(rest);
+    ordered
+}
 
-    for ch in input.chars() {
-        if ch == '\n' {
-            consecutive_newlines += 1;
-            if consecutive_newlines <= 2 {
-                result.push(ch);
-            }
-        } else if ch == '\r' {
-            // skip \r, we normalize to \n
-        } else {
-            consecutive_newlines = 0;
-            result.push(ch);
+/// Build a context for each node in a sibling list. `index` and
+/// `sibling_count` count sibling JSX elements with the same tag, so a
+/// `<Note>` between two `<Step>`s doesn't shift their numbering.
+fn sibling_contexts(nodes: &[MdxNode], ancestors: &[String]) -> Vec<RenderContext> {
+    fn tag(node: &MdxNode) -> Option<&str> {
+        match node {
+            MdxNode::JsxElement { tag, .. } => Some(tag),
+            _ => None,
         }
     }
+    let mut counts: HashMap<&str, usize> = HashMap::new();
+    for name in nodes.iter().filter_map(tag) {
+        *counts.entry(name).or_default() += 1;
+    }
+    let mut seen: HashMap<&str, usize> = HashMap::new();
+    nodes
+        .iter()
+        .map(|node| {
+            let (index, sibling_count) = match tag(node) {
+                Some(name) => {
+                    let index = seen.entry(name).or_default();
+                    *index += 1;
+                    (*index - 1, counts[name])
+                }
+                None => (0, 0),
+            };
+            RenderContext {
+                ancestors: ancestors.to_vec(),
+                index,
+                sibling_count,
+            }
+        })
+        .collect()
+}
 
-    // Trim trailing whitespace
-    let trimmed = result.trim_end();
-    let mut final_result = trimmed.to_string();
-    final_result.push('\n');
-    final_result
+/// Normalize whitespace outside code, drop leading blank lines and end the
+/// document with one newline.
+fn clean_blank_lines(input: &str, verbatim: &[String]) -> String {
+    let mut result = normalize_whitespace(input, verbatim).trim_start_matches('\n').trim_end().to_string();
+    result.push('\n');
+    result
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::parser::parse;
+    use crate::resolver::ResolveError;
     use crate::tokenizer::tokenize;
 
     fn run_transform(input: &str, config: &Config) -> String {
@@ -306,6 +675,7 @@ mod tests {
             "Callout".to_string(),
             ComponentTransform {
                 template: "> **{type}**: {children}".to_string(),
+                ..Default::default()
             },
         );
         let config = Config {
@@ -324,6 +694,7 @@ mod tests {
             "Badge".to_string(),
             ComponentTransform {
                 template: "{label}".to_string(),
+                ..Default::default()
             },
         );
         let config = Config {
@@ -342,6 +713,7 @@ mod tests {
             "_default".to_string(),
             ComponentTransform {
                 template: "{children}".to_string(),
+                ..Default::default()
             },
         );
         let config = Config {
@@ -352,6 +724,330 @@ mod tests {
         assert_eq!(result.trim(), "fallback content");
     }
 
+    #[test]
+    fn test_array_prop_in_template() {
+        let input = r#"<Features items={["Fast", "Safe"]} count={2} />"#;
+        let mut components = std::collections::HashMap::new();
+        components.insert(
+            "Features".to_string(),
+            ComponentTransform {
+                template: "{count} features:\n{items:list}".to_string(),
+                ..Default::default()
+            },
+        );
+        let config = Config {
+            components,
+            ..Default::default()
+        };
+        let result = run_transform(input, &config);
+        assert_eq!(result.trim(), "2 features:\n- Fast\n- Safe");
+    }
+
+    fn config_with_modes(modes: &[(&str, ComponentMode)]) -> Config {
+        let components = modes
+            .iter()
+            .map(|(tag, mode)| {
+                (
+                    tag.to_string(),
+                    ComponentTransform {
+                        mode: *mode,
+                        ..Default::default()
+                    },
+                )
+            })
+            .collect();
+        Config {
+            components,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_mode_drop_and_unwrap() {
+        let input = "A <Hidden>secret <b>x</b></Hidden>B <Wrap>kept **text**</Wrap>";
+        let config = config_with_modes(&[("Hidden", ComponentMode::Drop), ("Wrap", ComponentMode::Unwrap)]);
+        let result = run_transform(input, &config);
+        assert_eq!(result.trim(), "A B kept **text**");
+    }
+
+    #[test]
+    fn test_mode_html() {
+        let input = "<details open>\n<summary>More</summary>\n\nHidden **content**.\n</details>";
+        let config = config_with_modes(&[("details", ComponentMode::Html), ("summary", ComponentMode::Html)]);
+        let result = run_transform(input, &config);
+        assert_eq!(
+            result.trim(),
+            "<details open>\n\n<summary>More</summary>\n\nHidden **content**.\n\n</details>"
+        );
+    }
+
+    #[test]
+    fn test_mode_raw() {
+        let input = r#"Before <Chart data={[1, 2]} title="Sales">{total}</Chart> after"#;
+        let config = config_with_modes(&[("Chart", ComponentMode::Raw)]);
+        let result = run_transform(input, &config);
+        assert_eq!(
+            result.trim(),
+            r#"Before <Chart data={[1, 2]} title="Sales">{total}</Chart> after"#
+        );
+    }
+
+    #[test]
+    fn test_pattern_matched_components() {
+        let config = Config::from_toml(
+            r#"
+[components."Icon*"]
+template = "[icon]"
+
+[components."UI.*"]
+mode = "unwrap"
+
+[components.admonition]
+match = ["Note", "Tip"]
+template = "> {children}"
+
+[components.IconLogo]
+template = "LOGO"
+"#,
+        )
+        .unwrap();
+        let input = "<IconArrow /> <IconLogo /> <UI.Button>Go</UI.Button>\n\n<Tip>Hint</Tip>";
+        let result = run_transform(input, &config);
+        assert_eq!(result.trim(), "[icon] LOGO Go\n\n> Hint");
+    }
+
+    #[test]
+    fn test_context_in_templates() {
+        let config = Config::from_toml(
+            r#"
+[components.Steps]
+template = "{children}"
+
+[components.Step]
+template = "{$position}/{$count}. {title} ({$parent}, depth {$depth})"
+"#,
+        )
+        .unwrap();
+        let input = "<Steps>\n<Step title=\"Install\" />\n\n<Note>Tip</Note>\n\n<Step title=\"Run\" />\n</Steps>";
+        let result = run_transform(input, &config);
+        assert_eq!(
+            result.trim(),
+            "1/2. Install (Steps, depth 1)\n\nTip\n\n2/2. Run (Steps, depth 1)"
+        );
+    }
+
+    #[test]
+    fn test_context_passed_to_resolver() {
+        struct ContextResolver;
+        impl ComponentResolver for ContextResolver {
+            fn resolve(
+                &self,
+                tag: &str,
+                _props: &HashMap<String, Value>,
+                children: &str,
+                context: &RenderContext,
+            ) -> Option<String> {
+                match (tag, context.parent()) {
+                    ("Code", Some("Table")) => Some(format!("`{children}`")),
+                    ("Code", _) => Some(format!("```\n{children}\n```")),
+                    _ => None,
+                }
+            }
+        }
+        let input = "<Table><Code>x</Code></Table>\n\n<Code>y</Code>";
+        let doc = parse(tokenize(input).unwrap()).unwrap();
+        let result = transform_with_resolver(&doc, &Config::default(), &ContextResolver);
+        assert_eq!(result.trim(), "`x`\n\n```\ny\n```");
+    }
+
+    #[test]
+    fn test_indented_children_are_dedented() {
+        let input = "<Note>\n    Intro **text**.\n\n    <Inner>\n        nested\n    </Inner>\n\n    ```py\n    if a:\n      b()\n    ```\n</Note>\n";
+        let result = run_transform(input, &Config::default());
+        assert_eq!(result.trim(), "Intro **text**.\n\nnested\n\n```py\nif a:\n  b()\n```");
+    }
+
+    #[test]
+    fn test_code_blocks_keep_whitespace() {
+        let input = "Line one  \nline two\n\n\n\n```yaml\na: 1\n\n\n\nb:   \n  - c\n```\n\n<Note>\n  ```py\n  x = 1\n\n\n  y = 2\n  ```\n</Note>\n";
+        let result = run_transform(input, &Config::default());
+        assert_eq!(
+            result,
+            "Line one  \nline two\n\n```yaml\na: 1\n\n\n\nb:   \n  - c\n```\n\n```py\nx = 1\n\n\ny = 2\n```\n"
+        );
+    }
+
+    #[test]
+    fn test_raw_text_children_verbatim() {
+        let config = Config::from_toml("[components.CodeBlock]\ntemplate = \"```{language}\\n{children}\\n```\"").unwrap();
+        let input = "<CodeBlock language=\"ts\">\n  const m: Map<string, {a: 1}> = x;\n\n\n  f<T>();\n</CodeBlock>\n";
+        assert_eq!(
+            run_transform(input, &config),
+            "```ts\nconst m: Map<string, {a: 1}> = x;\n\n\nf<T>();\n```\n"
+        );
+
+        // Kept as written even when the element isn't rendered as code
+        let input = "<Mermaid>\ngraph TD  \n  A --> B\t\n\n\n\n  B --> C\n</Mermaid>\n\nText  \n\n\n";
+        assert_eq!(
+            run_transform(input, &Config::default()),
+            "graph TD  \n  A --> B\t\n\n\n\n  B --> C\n\nText\n"
+        );
+    }
+
+    #[test]
+    fn test_resolve_expressions() {
+        let config = Config::from_toml(
+            r#"
+variables = { latestVersion = "3.1.0", site = { url = "https://a.dev" }, props = { x = "*x*" }, h = "+ h-1" }
+
+[options]
+expression_handling = "resolve"
+unresolved_expressions = "preserve_raw"
+
+[components.Link]
+template = "[{children}]({href})"
+"#,
+        )
+        .unwrap();
+        let input = "---\ntitle: Guide\n---\nexport const pkg = \"mdx2md\";\n\n# {frontmatter.title}\n\nnpm i {pkg}@{latestVersion} {other}\n\n<Link href={site.url}>Home</Link>\n\n{props.x} {h}\n\n{h}\n";
+        assert_eq!(
+            run_transform(input, &config),
+            "---\ntitle: Guide\n---\n\n# Guide\n\nnpm i mdx2md@3.1.0 {other}\n\n[Home](https://a.dev)\n\n\\*x\\* + h-1\n\n\\+ h-1\n"
+        );
+    }
+
+    #[test]
+    fn test_evaluated_text_is_escaped() {
+        let config = Config::from_toml("[options]\nexpression_handling = \"evaluate\"").unwrap();
+        let input = "{\"*\"}literal{\"*\"} {\"<\"}div{\">\"} {1/0}\n\n{\"# x\"}\n";
+        assert_eq!(run_transform(input, &config), "\\*literal\\* \\<div\\> Infinity\n\n\\# x\n");
+    }
+
+    #[test]
+    fn test_evaluate_expressions() {
+        let config = Config::from_toml(
+            r#"
+variables = { version = "2.0" }
+
+[options]
+expression_handling = "evaluate"
+unresolved_expressions = "placeholder"
+
+[components.Badge]
+template = "[{label}]"
+"#,
+        )
+        .unwrap();
+        let input = "Use {'{'}braces{'}'} and {\"`\"}, wait {2 * 60}s for {`v${version}`} {version > '1' ? \"new\" : \"old\"} {fetch()} <Badge label={'v' + version} />\n";
+        assert_eq!(
+            run_transform(input, &config),
+            "Use \\{braces\\} and \\`, wait 120s for v2.0 new [expression] [v2.0]\n"
+        );
+    }
+
+    #[test]
+    fn test_expression_placeholder_and_identifier_rules() {
+        let config = Config::from_toml(
+            r#"
+[options]
+expression_handling = "placeholder"
+
+[expressions]
+placeholder = "[dynamic: {expr:code}]"
+attributes = "strip"
+identifiers = { version = "preserve_raw", "process.env" = "strip", "site" = "placeholder" }
+
+[components.Link]
+template = "[{children}]({href}){title}"
+"#,
+        )
+        .unwrap();
+        let input = "v{version} {user.name} key={process.env.KEY} {version + process.env.X}\n\n<Link href={site.url} title={version}>x</Link>\n";
+        assert_eq!(
+            run_transform(input, &config),
+            "v{version} [dynamic: `user.name`] key=\n\n[x](\\[dynamic: \\`site.url\\`\\])version\n"
+        );
+    }
+
+    #[test]
+    fn test_identifier_rules_cannot_be_bypassed() {
+        let config = Config::from_toml(
+            r#"
+[options]
+expression_handling = "preserve_raw"
+
+[expressions]
+identifiers = { "process.env" = "strip" }
+
+[components.Env]
+mode = "raw"
+
+[components.Card]
+template = "{type}"
+"#,
+        )
+        .unwrap();
+        let input = "{process[\"env\"].KEY}{process .env.KEY}{(process).env.X}{process[k]}a <Env key={process.env.KEY} ok={1 + 1}>{process.env.X} b</Env>\n\n<Card type={process.env.T} b=\"1\" />\n";
+        let doc = parse(tokenize(input).unwrap()).unwrap();
+        let (result, _) = transform_with_element_resolver(&doc, &config, &PropsResolver);
+        assert_eq!(result, "a <Env ok={1 + 1}> b</Env>\n\nb=1\n");
+    }
+
+    #[test]
+    fn test_prop_defaults_rename_map_required() {
+        let config = Config::from_toml(
+            r#"
+[components.Callout]
+template = "> **{type}**: {children} ({href})"
+rename = { variant = "type" }
+defaults = { type = "note" }
+required = ["href"]
+map.type = { warning = "Warning", note = "Note" }
+"#,
+        )
+        .unwrap();
+        let doc = parse(tokenize("<Callout variant=\"warning\" href=\"/a\">A</Callout>\n\n<Callout>B</Callout>").unwrap()).unwrap();
+        let (result, diagnostics) = transform_with_element_resolver(&doc, &config, &NoResolver);
+        assert_eq!(result, "> **Warning**: A (/a)\n\nB\n");
+        assert_eq!(diagnostics, vec![Diagnostic::error(Some("Callout"), "missing required prop `href`")]);
+    }
+
+    #[test]
+    fn test_prepared_props_in_every_mode() {
+        let config = Config::from_toml(
+            r#"
+[components.Box]
+mode = "html"
+rename = { kind = "class" }
+defaults = { role = "note" }
+map.class = { warn = "warning" }
+
+[components.Card]
+template = "{type}"
+rename = { variant = "type", b = "a" }
+defaults = { type = "note" }
+"#,
+        )
+        .unwrap();
+        let doc = parse(tokenize("<Box id=\"x\" kind=\"warn\">Hi</Box>\n\n<Card variant=\"tip\" b=\"1\" />").unwrap()).unwrap();
+        let (result, _) = transform_with_element_resolver(&doc, &config, &PropsResolver);
+        assert_eq!(result, "<Box id=\"x\" class=\"warning\" role=\"note\">Hi</Box>\n\na=1, type=tip\n");
+    }
+
+    /// Lists the props it receives for `<Card>`.
+    struct PropsResolver;
+
+    impl ElementResolver for PropsResolver {
+        fn resolve(&self, element: &JsxElement<'_>, cx: &ResolveContext<'_>) -> Result<Option<String>, ResolveError> {
+            if element.tag != "Card" {
+                return Ok(None);
+            }
+            let mut props: Vec<String> = cx.props().iter().map(|(k, v)| format!("{k}={v}")).collect();
+            props.sort();
+            Ok(Some(props.join(", ")))
+        }
+    }
+
     #[test]
     fn test_expression_strip() {
         let input = "The answer is {40 + 2}.";
@@ -374,6 +1070,31 @@ mod tests {
         assert_eq!(result.trim(), "The answer is {40 + 2}.");
     }
 
+    #[test]
+    fn test_invalid_component_patterns_fail_closed() {
+        let doc = parse(tokenize("<InternalNote>Secret</InternalNote>\n").unwrap()).unwrap();
+
+        let mut config = Config::default();
+        config.components.insert("/(bad/".to_string(), ComponentTransform::default());
+        let (out, diagnostics) = transform_with_element_resolver(&doc, &config, &NoResolver);
+        assert_eq!(out, "");
+        assert!(diagnostics[0].message.contains("invalid component pattern"));
+
+        // A filter that can't be compiled must not let the content through
+        let mut config = Config::default();
+        config.filters.push(FilterRule {
+            component: Some("/(Internal/".to_string()),
+            prop: None,
+            frontmatter: None,
+            target: None,
+            action: FilterAction::Drop,
+        });
+        let (out, diagnostics) = transform_with_element_resolver(&doc, &config, &NoResolver);
+        assert_eq!(out, "");
+        assert!(diagnostics[0].message.contains("invalid component pattern"));
+        assert!(crate::convert("<InternalNote>Secret</InternalNote>\n", &config).is_err());
+    }
+
     #[test]
     fn test_kitchen_sink_layer1() {
         let input = std::fs::read_to_string("tests/fixtures/kitchen_sink.mdx").unwrap();
@@ -0,0 +1,530 @@
+use serde::de::{Deserialize, Deserializer, MapAccess, SeqAccess, Visitor};
+use std::fmt;
+
+/// A typed prop value. Literal JS expressions in attributes (`{[1, 2]}`,
+/// `{true}`, `{{ a: 1 }}`) are parsed into structured values; anything else
+/// is kept as raw expression source.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<Value>),
+    /// Object entries in source order.
+    Object(Vec<(String, Value)>),
+    /// A non-literal JS expression, kept verbatim (without braces).
+    Expression(String),
+}
+
+impl Value {
+    /// Look up a dotted path (`a.b.0`) inside objects and arrays.
+    /// An empty path returns `self`.
+    pub fn get_path(&self, path: &str) -> Option<&Value> {
+        if path.is_empty() {
+            return Some(self);
+        }
+        let mut current = self;
+        for segment in path.split('.') {
+            current = current.get(segment)?;
+        }
+        Some(current)
+    }
+
+    /// Look up a single key (objects) or index (arrays).
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        match self {
+            Value::Object(entries) => entries.iter().rev().find(|(k, _)| k == key).map(|(_, v)| v),
+            Value::Array(items) => key.parse::<usize>().ok().and_then(|i| items.get(i)),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Value::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    pub fn as_expression(&self) -> Option<&str> {
+        match self {
+            Value::Expression(src) => Some(src),
+            _ => None,
+        }
+    }
+
+    /// JS-style truthiness. Raw expressions are considered truthy.
+    pub fn is_truthy(&self) -> bool {
+        match self {
+            Value::Null => false,
+            Value::Bool(b) => *b,
+            Value::Number(n) => *n != 0.0 && !n.is_nan(),
+            Value::String(s) => !s.is_empty(),
+            Value::Array(_) | Value::Object(_) | Value::Expression(_) => true,
+        }
+    }
+}
+
+/// Text rendering used by templates: arrays are joined with `, `, objects
+/// render as `key: value` pairs, and `null` renders as nothing.
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Null => Ok(()),
+            Value::Bool(b) => write!(f, "{b}"),
+            Value::Number(n) => f.write_str(&js_number(*n)),
+            Value::String(s) => f.write_str(s),
+            Value::Array(items) => {
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(", ")?;
+                    }
+                    write!(f, "{item}")?;
+                }
+                Ok(())
+            }
+            Value::Object(entries) => {
+                for (i, (key, value)) in entries.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(", ")?;
+                    }
+                    write!(f, "{key}: {value}")?;
+                }
+                Ok(())
+            }
+            Value::Expression(e) => f.write_str(e),
+        }
+    }
+}
+
+/// JS `Number.prototype.toString()`: `NaN`, `Infinity`, no negative zero,
+/// and exponent notation outside `1e-6..1e21`.
+fn js_number(n: f64) -> String {
+    if n.is_nan() {
+        "NaN".to_string()
+    } else if n.is_infinite() {
+        if n > 0.0 { "Infinity" } else { "-Infinity" }.to_string()
+    } else if n == 0.0 {
+        "0".to_string()
+    } else if n.abs() >= 1e21 || n.abs() < 1e-6 {
+        let exp = format!("{n:e}");
+        match exp.split_once('e') {
+            Some((mantissa, e)) if !e.starts_with('-') => format!("{mantissa}e+{e}"),
+            _ => exp,
+        }
+    } else {
+        n.to_string()
+    }
+}
+
+/// Values in config (e.g. component `defaults`) deserialize from any
+/// self-describing format: tables become objects, in source order.
+impl<'de> Deserialize<'de> for Value {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(ValueVisitor)
+    }
+}
+
+struct ValueVisitor;
+
+impl<'de> Visitor<'de> for ValueVisitor {
+    type Value = Value;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("a value")
+    }
+
+    fn visit_unit<E>(self) -> Result<Value, E> {
+        Ok(Value::Null)
+    }
+
+    fn visit_none<E>(self) -> Result<Value, E> {
+        Ok(Value::Null)
+    }
+
+    fn visit_some<D: Deserializer<'de>>(self, deserializer: D) -> Result<Value, D::Error> {
+        Value::deserialize(deserializer)
+    }
+
+    fn visit_bool<E>(self, v: bool) -> Result<Value, E> {
+        Ok(Value::Bool(v))
+    }
+
+    fn visit_i64<E>(self, v: i64) -> Result<Value, E> {
+        Ok(Value::Number(v as f64))
+    }
+
+    fn visit_u64<E>(self, v: u64) -> Result<Value, E> {
+        Ok(Value::Number(v as f64))
+    }
+
+    fn visit_f64<E>(self, v: f64) -> Result<Value, E> {
+        Ok(Value::Number(v))
+    }
+
+    fn visit_str<E>(self, v: &str) -> Result<Value, E> {
+        Ok(Value::String(v.to_string()))
+    }
+
+    fn visit_string<E>(self, v: String) -> Result<Value, E> {
+        Ok(Value::String(v))
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Value, A::Error> {
+        let mut items = Vec::new();
+        while let Some(item) = seq.next_element()? {
+            items.push(item);
+        }
+        Ok(Value::Array(items))
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Value, A::Error> {
+        let mut entries = Vec::new();
+        while let Some(entry) = map.next_entry()? {
+            entries.push(entry);
+        }
+        Ok(Value::Object(entries))
+    }
+}
+
+/// Parse a JS literal expression: strings, numbers, booleans, `null`/`undefined`,
+/// arrays, objects and template literals without `${}` interpolation.
+/// Returns `None` for anything that is not a plain literal.
+pub fn parse_literal(src: &str) -> Option<Value> {
+    let mut parser = LiteralParser {
+        chars: src.chars().collect(),
+        pos: 0,
+    };
+    parser.skip_ws();
+    let value = parser.parse_value()?;
+    parser.skip_ws();
+    if parser.pos == parser.chars.len() {
+        Some(value)
+    } else {
+        None
+    }
+}
+
+struct LiteralParser {
+    chars: Vec<char>,
+    pos: usize,
+}
+
+impl LiteralParser {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.pos += 1;
+        Some(c)
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        if self.peek() == Some(c) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Skip whitespace and `//` / `/* */` comments.
+    fn skip_ws(&mut self) {
+        loop {
+            match self.peek() {
+                Some(c) if c.is_whitespace() => self.pos += 1,
+                Some('/') if self.chars.get(self.pos + 1) == Some(&'/') => {
+                    while let Some(c) = self.bump() {
+                        if c == '\n' {
+                            break;
+                        }
+                    }
+                }
+                Some('/') if self.chars.get(self.pos + 1) == Some(&'*') => {
+                    self.pos += 2;
+                    while self.pos < self.chars.len() {
+                        if self.chars[self.pos] == '*' && self.chars.get(self.pos + 1) == Some(&'/') {
+                            self.pos += 2;
+                            break;
+                        }
+                        self.pos += 1;
+                    }
+                }
+                _ => return,
+            }
+        }
+    }
+
+    fn parse_value(&mut self) -> Option<Value> {
+        match self.peek()? {
+            '"' | '\'' => self.parse_string().map(Value::String),
+            '`' => self.parse_template_literal().map(Value::String),
+            '[' => self.parse_array(),
+            '{' => self.parse_object(),
+            c if c.is_ascii_digit() || c == '-' || c == '+' || c == '.' => self.parse_number().map(Value::Number),
+            c if is_ident_start(c) => match self.parse_ident().as_str() {
+                "true" => Some(Value::Bool(true)),
+                "false" => Some(Value::Bool(false)),
+                "null" | "undefined" => Some(Value::Null),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    fn parse_ident(&mut self) -> String {
+        let mut ident = String::new();
+        while let Some(c) = self.peek() {
+            if is_ident_continue(c) {
+                ident.push(c);
+                self.pos += 1;
+            } else {
+                break;
+            }
+        }
+        ident
+    }
+
+    fn parse_string(&mut self) -> Option<String> {
+        let quote = self.bump()?;
+        let mut out = String::new();
+        loop {
+            match self.bump()? {
+                c if c == quote => return Some(out),
+                '\n' => return None,
+                '\\' => self.parse_escape(&mut out)?,
+                c => out.push(c),
+            }
+        }
+    }
+
+    fn parse_template_literal(&mut self) -> Option<String> {
+        self.bump();
+        let mut out = String::new();
+        loop {
+            match self.bump()? {
+                '`' => return Some(out),
+                '$' if self.peek() == Some('{') => return None,
+                '\\' => self.parse_escape(&mut out)?,
+                c => out.push(c),
+            }
+        }
+    }
+
+    fn parse_escape(&mut self, out: &mut String) -> Option<()> {
+        match self.bump()? {
+            'n' => out.push('\n'),
+            't' => out.push('\t'),
+            'r' => out.push('\r'),
+            'b' => out.push('\u{8}'),
+            'f' => out.push('\u{c}'),
+            'v' => out.push('\u{b}'),
+            '0' => out.push('\0'),
+            '\n' => {}
+            'x' => {
+                let hex: String = (0..2).map(|_| self.bump()).collect::<Option<_>>()?;
+                out.push(char::from_u32(u32::from_str_radix(&hex, 16).ok()?)?);
+            }
+            'u' => {
+                let code = if self.eat('{') {
+                    let mut hex = String::new();
+                    while let Some(c) = self.bump() {
+                        if c == '}' {
+                            break;
+                        }
+                        hex.push(c);
+                    }
+                    u32::from_str_radix(&hex, 16).ok()?
+                } else {
+                    let hex: String = (0..4).map(|_| self.bump()).collect::<Option<_>>()?;
+                    u32::from_str_radix(&hex, 16).ok()?
+                };
+                out.push(char::from_u32(code).unwrap_or('\u{FFFD}'));
+            }
+            c => out.push(c),
+        }
+        Some(())
+    }
+
+    fn parse_number(&mut self) -> Option<f64> {
+        let negative = if self.eat('-') {
+            true
+        } else {
+            self.eat('+');
+            false
+        };
+
+        let radix = match (self.peek(), self.chars.get(self.pos + 1)) {
+            (Some('0'), Some('x' | 'X')) => 16,
+            (Some('0'), Some('o' | 'O')) => 8,
+            (Some('0'), Some('b' | 'B')) => 2,
+            _ => 10,
+        };
+
+        let value = if radix != 10 {
+            self.pos += 2;
+            let mut digits = String::new();
+            while let Some(c) = self.peek() {
+                if c.is_digit(radix) {
+                    digits.push(c);
+                } else if c != '_' {
+                    break;
+                }
+                self.pos += 1;
+            }
+            i64::from_str_radix(&digits, radix).ok()? as f64
+        } else {
+            let mut text = String::new();
+            while let Some(c) = self.peek() {
+                match c {
+                    '0'..='9' | '.' => text.push(c),
+                    'e' | 'E' => {
+                        text.push(c);
+                        if let Some(sign @ ('+' | '-')) = self.chars.get(self.pos + 1).copied() {
+                            text.push(sign);
+                            self.pos += 1;
+                        }
+                    }
+                    '_' => {}
+                    _ => break,
+                }
+                self.pos += 1;
+            }
+            text.parse::<f64>().ok()?
+        };
+
+        Some(if negative { -value } else { value })
+    }
+
+    fn parse_array(&mut self) -> Option<Value> {
+        self.bump();
+        let mut items = Vec::new();
+        loop {
+            self.skip_ws();
+            if self.eat(']') {
+                return Some(Value::Array(items));
+            }
+            items.push(self.parse_value()?);
+            self.skip_ws();
+            if !self.eat(',') {
+                self.skip_ws();
+                return self.eat(']').then_some(Value::Array(items));
+            }
+        }
+    }
+
+    fn parse_object(&mut self) -> Option<Value> {
+        self.bump();
+        let mut entries: Vec<(String, Value)> = Vec::new();
+        loop {
+            self.skip_ws();
+            if self.eat('}') {
+                return Some(Value::Object(entries));
+            }
+            let key = match self.peek()? {
+                '"' | '\'' => self.parse_string()?,
+                c if c.is_ascii_digit() => self.parse_number()?.to_string(),
+                c if is_ident_start(c) => self.parse_ident(),
+                _ => return None,
+            };
+            self.skip_ws();
+            if !self.eat(':') {
+                return None;
+            }
+            self.skip_ws();
+            let value = self.parse_value()?;
+            entries.retain(|(k, _)| *k != key);
+            entries.push((key, value));
+            self.skip_ws();
+            if !self.eat(',') {
+                self.skip_ws();
+                return self.eat('}').then_some(Value::Object(entries));
+            }
+        }
+    }
+}
+
+fn is_ident_start(c: char) -> bool {
+    c.is_alphabetic() || c == '_' || c == '$'
+}
+
+fn is_ident_continue(c: char) -> bool {
+    c.is_alphanumeric() || c == '_' || c == '$'
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn s(v: &str) -> Value {
+        Value::String(v.to_string())
+    }
+
+    #[test]
+    fn test_scalars() {
+        assert_eq!(parse_literal("true"), Some(Value::Bool(true)));
+        assert_eq!(parse_literal(" false "), Some(Value::Bool(false)));
+        assert_eq!(parse_literal("null"), Some(Value::Null));
+        assert_eq!(parse_literal("undefined"), Some(Value::Null));
+        assert_eq!(parse_literal("42"), Some(Value::Number(42.0)));
+        assert_eq!(parse_literal("-1.5e2"), Some(Value::Number(-150.0)));
+        assert_eq!(parse_literal("0xff"), Some(Value::Number(255.0)));
+        assert_eq!(parse_literal("1_000"), Some(Value::Number(1000.0)));
+    }
+
+    #[test]
+    fn test_strings() {
+        assert_eq!(parse_literal(r#""hello""#), Some(s("hello")));
+        assert_eq!(parse_literal(r"'it\'s'"), Some(s("it's")));
+        assert_eq!(parse_literal(r#""a\nb""#), Some(s("a\nb")));
+        assert_eq!(parse_literal(r#""é\u{1F600}""#), Some(s("é😀")));
+        assert_eq!(parse_literal("`plain template`"), Some(s("plain template")));
+        assert_eq!(parse_literal("`v${version}`"), None);
+    }
+
+    #[test]
+    fn test_array_and_object() {
+        assert_eq!(
+            parse_literal(r#"["a", 'b', 3,]"#),
+            Some(Value::Array(vec![s("a"), s("b"), Value::Number(3.0)]))
+        );
+        assert_eq!(
+            parse_literal("{ a: 1, 'b-c': [true], \"d\": { e: null } }"),
+            Some(Value::Object(vec![
+                ("a".to_string(), Value::Number(1.0)),
+                ("b-c".to_string(), Value::Array(vec![Value::Bool(true)])),
+                ("d".to_string(), Value::Object(vec![("e".to_string(), Value::Null)])),
+            ]))
+        );
+    }
+
+    #[test]
+    fn test_non_literals_rejected() {
+        assert_eq!(parse_literal("40 + 2"), None);
+        assert_eq!(parse_literal("process.env.KEY"), None);
+        assert_eq!(parse_literal("[a, b]"), None);
+        assert_eq!(parse_literal("{ a }"), None);
+        assert_eq!(parse_literal("fn()"), None);
+        assert_eq!(parse_literal(""), None);
+    }
+
+    #[test]
+    fn test_display_and_paths() {
+        let v = parse_literal(r#"{ items: ["x", "y"], n: 3, ok: true }"#).unwrap();
+        assert_eq!(v.get_path("items").unwrap().to_string(), "x, y");
+        assert_eq!(v.get_path("items.1").unwrap().to_string(), "y");
+        assert_eq!(v.get_path("n").unwrap().to_string(), "3");
+        assert_eq!(v.get_path("missing"), None);
+        assert_eq!(v.to_string(), "items: x, y, n: 3, ok: true");
+        assert_eq!(Value::Number(0.5).to_string(), "0.5");
+    }
+
+    #[test]
+    fn test_deserialize_from_toml() {
+        let v: Value = toml::from_str("list = [1, true, \"x\"]\nmeta = { title = \"Hi\" }").unwrap();
+        assert_eq!(v, parse_literal(r#"{ list: [1, true, "x"], meta: { title: "Hi" } }"#).unwrap());
+    }
+}
@@ -0,0 +1,310 @@
+use crate::ast::MdxNode;
+use pulldown_cmark::{Event, Parser, Tag};
+use std::borrow::Cow;
+use std::ops::Range;
+
+/// Remove the common indentation from the Markdown children of a JSX element.
+///
+/// Authors indent component children for readability; left as is, four or
+/// more spaces turn them into indented code blocks once the element is
+/// unwrapped. The indentation is measured over lines that start inside a
+/// Markdown child, skipping blank lines and the content of fenced code blocks
+/// (which may legitimately be indented further, or less). Fenced content is
+/// stripped by at most the common indentation, so code keeps its own layout.
+///
+/// Returns the dedented text for each `Markdown` child and `None` for others.
+pub(crate) fn dedent_children(children: &[MdxNode]) -> Vec<Option<String>> {
+    let indent = common_indent(children);
+    let mut fence = FenceState::default();
+    let mut at_line_start = false;
+
+    children
+        .iter()
+        .enumerate()
+        .map(|(i, child)| {
+            let MdxNode::Markdown(text) = child else {
+                at_line_start = false;
+                return None;
+            };
+            let mut out = String::with_capacity(text.len());
+            let last = text.matches('\n').count();
+            for (j, line) in text.split('\n').enumerate() {
+                if j > 0 {
+                    out.push('\n');
+                    at_line_start = true;
+                }
+                if !at_line_start {
+                    out.push_str(line);
+                    continue;
+                }
+                let stripped = strip_indent(line, indent);
+                if !is_blank(line, j == last, children.get(i + 1)) {
+                    fence.update(&stripped);
+                }
+                out.push_str(&stripped);
+            }
+            // Text after the last newline continues the line
+            at_line_start = text.ends_with('\n') && at_line_start;
+            Some(out)
+        })
+        .collect()
+}
+
+fn common_indent(children: &[MdxNode]) -> usize {
+    let mut fence = FenceState::default();
+    let mut at_line_start = false;
+    let mut indent: Option<usize> = None;
+
+    for (i, child) in children.iter().enumerate() {
+        let MdxNode::Markdown(text) = child else {
+            at_line_start = false;
+            continue;
+        };
+        let last = text.matches('\n').count();
+        for (j, line) in text.split('\n').enumerate() {
+            if j > 0 {
+                at_line_start = true;
+            }
+            if !at_line_start || is_blank(line, j == last, children.get(i + 1)) {
+                continue;
+            }
+            let width = indent_width(line);
+            let rest = line.trim_start_matches([' ', '\t']);
+            if !fence.is_open() || fence.closes(rest) {
+                indent = Some(indent.map_or(width, |n| n.min(width)));
+            }
+            fence.update(rest);
+        }
+        at_line_start = text.ends_with('\n') && at_line_start;
+    }
+
+    indent.unwrap_or(0)
+}
+
+/// Whether a line is blank. The last line of a Markdown node is not blank
+/// when an element or expression follows on the same line.
+fn is_blank(line: &str, is_last: bool, next: Option<&MdxNode>) -> bool {
+    if !line.trim().is_empty() {
+        return false;
+    }
+    !(is_last && next.is_some_and(|n| !matches!(n, MdxNode::Markdown(_))))
+}
+
+const TAB_STOP: usize = 4;
+
+/// Width of the leading whitespace in columns, with tabs advancing to the
+/// next tab stop as in CommonMark.
+fn indent_width(line: &str) -> usize {
+    line.chars()
+        .take_while(|c| matches!(c, ' ' | '\t'))
+        .fold(0, |col, c| if c == '\t' { col + TAB_STOP - col % TAB_STOP } else { col + 1 })
+}
+
+/// Remove up to `indent` columns of leading whitespace. A tab that spans the
+/// boundary is replaced by the spaces left over after stripping.
+fn strip_indent(line: &str, indent: usize) -> Cow<'_, str> {
+    let mut col = 0;
+    for (i, c) in line.char_indices() {
+        if col >= indent {
+            return Cow::Borrowed(&line[i..]);
+        }
+        match c {
+            ' ' => col += 1,
+            '\t' => {
+                let next = col + TAB_STOP - col % TAB_STOP;
+                if next > indent {
+                    return Cow::Owned(" ".repeat(next - indent) + &line[i + 1..]);
+                }
+                col = next;
+            }
+            _ => return Cow::Borrowed(&line[i..]),
+        }
+    }
+    Cow::Borrowed("")
+}
+
+/// Tracks whether we are inside a fenced code block.
+#[derive(Default)]
+pub(crate) struct FenceState {
+    open: Option<(char, usize)>,
+}
+
+impl FenceState {
+    pub(crate) fn is_open(&self) -> bool {
+        self.open.is_some()
+    }
+
+    fn closes(&self, line: &str) -> bool {
+        match (self.open, fence_marker(line)) {
+            (Some((c, n)), Some((mc, mn, rest))) => c == mc && mn >= n && rest.trim().is_empty(),
+            _ => false,
+        }
+    }
+
+    /// Feed a line with its common indentation removed.
+    pub(crate) fn update(&mut self, line: &str) {
+        let line = line.trim_start_matches([' ', '\t']);
+        if self.is_open() {
+            if self.closes(line) {
+                self.open = None;
+            }
+        } else if let Some((c, n, _)) = fence_marker(line) {
+            self.open = Some((c, n));
+        }
+    }
+}
+
+/// A run of three or more backticks or tildes at the start of a line.
+fn fence_marker(line: &str) -> Option<(char, usize, &str)> {
+    let line = line.trim_start_matches([' ', '\t']);
+    let c = line.chars().next().filter(|c| matches!(c, '`' | '~'))?;
+    let n = line.len() - line.trim_start_matches(c).len();
+    (n >= 3).then(|| (c, n, &line[n..]))
+}
+
+/// Normalize whitespace in rendered Markdown without touching code.
+///
+/// Converts CRLF and lone CR to LF, collapses runs of blank lines into one,
+/// and trims trailing whitespace. Lines inside fenced or indented code blocks
+/// and multi-line code spans are kept verbatim, as are lines within any
+/// occurrence of a `verbatim` text (the content of raw-text elements). Hard
+/// line breaks (two or more trailing spaces before another line of text) are
+/// preserved.
+pub(crate) fn normalize_whitespace(input: &str, verbatim: &[String]) -> String {
+    let input = input.replace("\r\n", "\n").replace('\r', "\n");
+    // Ranges are sorted and lines are visited in order, so a cursor over
+    // them is enough
+    let mut code = code_ranges(&input);
+    for text in verbatim.iter().filter(|text| text.contains('\n')) {
+        let text = text.replace("\r\n", "\n").replace('\r', "\n");
+        code.extend(input.match_indices(&text).map(|(start, _)| start..start + text.len()));
+    }
+    code.sort_by_key(|r| r.start);
+    let mut next_code = 0;
+
+    let lines: Vec<&str> = input.split('\n').collect();
+    let mut out: Vec<&str> = Vec::with_capacity(lines.len());
+    let mut offset = 0;
+    let mut blank_run = 0;
+    for (i, line) in lines.iter().enumerate() {
+        let end = offset + line.len();
+        offset = end + 1;
+        while code.get(next_code).is_some_and(|r| r.end <= end) {
+            next_code += 1;
+        }
+        if code.get(next_code).is_some_and(|r| r.contains(&end)) {
+            blank_run = 0;
+            out.push(line);
+            continue;
+        }
+        let trimmed = line.trim_end();
+        if trimmed.is_empty() {
+            blank_run += 1;
+            if blank_run == 1 {
+                out.push("");
+            }
+            continue;
+        }
+        blank_run = 0;
+        let next_is_text = lines.get(i + 1).is_some_and(|next| !next.trim().is_empty());
+        if next_is_text && line.ends_with("  ") {
+            out.push(line);
+        } else {
+            out.push(trimmed);
+        }
+    }
+    out.join("\n")
+}
+
+/// Byte ranges of code blocks and code spans.
+fn code_ranges(input: &str) -> Vec<Range<usize>> {
+    Parser::new(input)
+        .into_offset_iter()
+        .filter_map(|(event, range)| match event {
+            Event::Start(Tag::CodeBlock(_)) | Event::Code(_) => Some(range),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Drop indentation left at the end of a Markdown child before the next
+/// element or expression (`"text\n    "` before `<Tag>`).
+pub(crate) fn trim_trailing_indent(text: &str) -> &str {
+    match text.rfind('\n') {
+        Some(i) if text[i + 1..].trim().is_empty() => &text[..=i],
+        _ => text,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn md(s: &str) -> MdxNode {
+        MdxNode::Markdown(s.to_string())
+    }
+
+    fn dedent(children: &[MdxNode]) -> Vec<String> {
+        dedent_children(children).into_iter().flatten().collect()
+    }
+
+    #[test]
+    fn test_dedent_four_spaces() {
+        let out = dedent(&[md("\n    First paragraph.\n\n    - item\n      nested\n")]);
+        assert_eq!(out, vec!["\nFirst paragraph.\n\n- item\n  nested\n"]);
+    }
+
+    #[test]
+    fn test_fence_content_keeps_relative_indent() {
+        let input = "\n    ```rust\n    fn main() {\n        run();\n    }\n    ```\n";
+        let out = dedent(&[md(input)]);
+        assert_eq!(out, vec!["\n```rust\nfn main() {\n    run();\n}\n```\n"]);
+    }
+
+    #[test]
+    fn test_fence_content_does_not_lower_indent() {
+        let input = "\n    Text\n\n    ```\nflush left\n    ```\n";
+        let out = dedent(&[md(input)]);
+        assert_eq!(out, vec!["\nText\n\n```\nflush left\n```\n"]);
+    }
+
+    #[test]
+    fn test_dedent_mixed_tabs_and_spaces() {
+        let out = dedent(&[md("\n\tTabbed\n    Spaced\n  \t- item\n\t  nested\n")]);
+        assert_eq!(out, vec!["\nTabbed\nSpaced\n- item\n  nested\n"]);
+        let out = dedent(&[md("\n  Two\n\tTab\n")]);
+        assert_eq!(out, vec!["\nTwo\n  Tab\n"]);
+    }
+
+    #[test]
+    fn test_line_with_element_counts() {
+        let children = [
+            md("\n  Intro\n  "),
+            MdxNode::JsxElement {
+                tag: "B".to_string(),
+                attributes: vec![],
+                children: vec![],
+                self_closing: true,
+                span: None,
+            },
+            md(" after\n"),
+        ];
+        assert_eq!(dedent(&children), vec!["\nIntro\n", " after\n"]);
+    }
+
+    #[test]
+    fn test_normalize_keeps_code_blocks() {
+        let input = "Text  \r\nmore\n\n\n\n```py\ndef f():\n    x = 1   \n\n\n    return x\n```\n\n\n    indented\n\n\n    code\n";
+        assert_eq!(
+            normalize_whitespace(input, &[]),
+            "Text  \nmore\n\n```py\ndef f():\n    x = 1   \n\n\n    return x\n```\n\n    indented\n\n\n    code\n"
+        );
+    }
+
+    #[test]
+    fn test_normalize_trims_trailing_spaces() {
+        assert_eq!(normalize_whitespace("a \t\n   \nb  \n", &[]), "a\n\nb\n");
+        assert_eq!(normalize_whitespace("`a  \nb`", &[]), "`a  \nb`");
+        assert_eq!(normalize_whitespace("a \rb\r\r\r\rc", &[]), "a\nb\n\nc");
+    }
+}
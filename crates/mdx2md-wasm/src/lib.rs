@@ -1,6 +1,8 @@
 use js_sys::{Array, Function, Object, Reflect};
 use mdx2md_core::config::*;
-use mdx2md_core::ComponentResolver;
+use mdx2md_core::matcher::ComponentMatcher;
+use mdx2md_core::value::Value;
+use mdx2md_core::{ComponentResolver, RenderContext};
 use std::collections::HashMap;
 use wasm_bindgen::prelude::*;
 
@@ -15,30 +17,33 @@ pub fn convert(mdx: &str, options: JsValue) -> Result<String, JsError> {
     if js_resolvers.is_empty() {
         mdx2md_core::convert(mdx, &config).map_err(|e| JsError::new(&e.0))
     } else {
-        let resolver = JsComponentResolver {
-            callbacks: js_resolvers,
-        };
+        let mut callbacks = ComponentMatcher::new();
+        for (pattern, func) in js_resolvers {
+            callbacks.insert(&pattern, func).map_err(|e| JsError::new(&e.message))?;
+        }
+        let resolver = JsComponentResolver { callbacks };
         mdx2md_core::convert_with_resolver(mdx, &config, &resolver)
             .map_err(|e| JsError::new(&e.0))
     }
 }
 
 struct JsComponentResolver {
-    callbacks: HashMap<String, Function>,
+    callbacks: ComponentMatcher<Function>,
 }
 
 impl ComponentResolver for JsComponentResolver {
     fn resolve(
         &self,
         tag: &str,
-        props: &HashMap<String, String>,
+        props: &HashMap<String, Value>,
         children: &str,
+        context: &RenderContext,
     ) -> Option<String> {
-        let func = self.callbacks.get(tag).or_else(|| self.callbacks.get("_default"))?;
+        let func = self.callbacks.find(tag)?;
 
         let js_props = Object::new();
         for (key, value) in props {
-            Reflect::set(&js_props, &JsValue::from_str(key), &JsValue::from_str(value)).ok();
+            Reflect::set(&js_props, &JsValue::from_str(key), &to_js_value(value)).ok();
         }
         Reflect::set(
             &js_props,
@@ -47,11 +52,74 @@ impl ComponentResolver for JsComponentResolver {
         )
         .ok();
 
-        let result = func.call1(&JsValue::NULL, &js_props).ok()?;
+        let js_context = Object::new();
+        let parent = context.parent().map_or(JsValue::NULL, JsValue::from_str);
+        let ancestors: Array = context.ancestors.iter().map(|a| JsValue::from_str(a)).collect();
+        Reflect::set(&js_context, &JsValue::from_str("parent"), &parent).ok();
+        Reflect::set(&js_context, &JsValue::from_str("ancestors"), &ancestors).ok();
+        Reflect::set(&js_context, &JsValue::from_str("depth"), &JsValue::from(context.depth() as u32)).ok();
+        Reflect::set(&js_context, &JsValue::from_str("index"), &JsValue::from(context.index as u32)).ok();
+        Reflect::set(&js_context, &JsValue::from_str("count"), &JsValue::from(context.sibling_count as u32)).ok();
+
+        let result = func.call2(&JsValue::NULL, &js_props, &js_context).ok()?;
         result.as_string()
     }
 }
 
+/// Convert a typed prop value into a real JS value (arrays, objects, numbers...).
+/// Non-literal expressions are passed as their source string.
+fn to_js_value(value: &Value) -> JsValue {
+    match value {
+        Value::Null => JsValue::NULL,
+        Value::Bool(b) => JsValue::from_bool(*b),
+        Value::Number(n) => JsValue::from_f64(*n),
+        Value::String(s) | Value::Expression(s) => JsValue::from_str(s),
+        Value::Array(items) => items.iter().map(to_js_value).collect::<Array>().into(),
+        Value::Object(entries) => {
+            let obj = Object::new();
+            for (key, item) in entries {
+                Reflect::set(&obj, &JsValue::from_str(key), &to_js_value(item)).ok();
+            }
+            obj.into()
+        }
+    }
+}
+
+/// Convert a plain JS value (from options) into a [`Value`]. Functions and
+/// other non-data values become `null`.
+fn from_js_value(value: &JsValue) -> Value {
+    if let Some(b) = value.as_bool() {
+        Value::Bool(b)
+    } else if let Some(n) = value.as_f64() {
+        Value::Number(n)
+    } else if let Some(s) = value.as_string() {
+        Value::String(s)
+    } else if Array::is_array(value) {
+        Value::Array(Array::from(value).iter().map(|item| from_js_value(&item)).collect())
+    } else if value.is_object() && !value.is_function() {
+        let entries = Object::entries(value.unchecked_ref())
+            .iter()
+            .filter_map(|entry| {
+                let entry = Array::from(&entry);
+                Some((entry.get(0).as_string()?, from_js_value(&entry.get(1))))
+            })
+            .collect();
+        Value::Object(entries)
+    } else {
+        Value::Null
+    }
+}
+
+fn parse_expression_handling(name: &str) -> ExpressionHandling {
+    match name {
+        "preserve" => ExpressionHandling::PreserveRaw,
+        "placeholder" => ExpressionHandling::Placeholder,
+        "resolve" => ExpressionHandling::Resolve,
+        "evaluate" => ExpressionHandling::Evaluate,
+        _ => ExpressionHandling::Strip,
+    }
+}
+
 /// Parse the JS options object into a Config + map of JS function callbacks.
 fn parse_options(options: &JsValue) -> Result<(Config, HashMap<String, Function>), String> {
     let mut config = Config::default();
@@ -67,14 +135,85 @@ fn parse_options(options: &JsValue) -> Result<(Config, HashMap<String, Function>
     if let Some(v) = get_bool(options, "preserveFrontmatter") {
         config.options.preserve_frontmatter = v;
     }
+    if let Some(v) = get_bool(options, "htmlToMarkdown") {
+        config.options.html_to_markdown = v;
+    }
     if let Some(v) = get_string(options, "expressionHandling") {
-        config.options.expression_handling = match v.as_str() {
-            "strip" => ExpressionHandling::Strip,
-            "preserve" => ExpressionHandling::PreserveRaw,
-            "placeholder" => ExpressionHandling::Placeholder,
-            _ => ExpressionHandling::Strip,
+        config.options.expression_handling = parse_expression_handling(&v);
+    }
+    if let Some(v) = get_string(options, "unresolvedExpressions") {
+        config.options.unresolved_expressions = parse_expression_handling(&v);
+    }
+    if let Some(v) = get_string(options, "expressionPlaceholder") {
+        config.expressions.placeholder = v;
+    }
+    if let Some(v) = get_string(options, "expressionAttributes") {
+        config.expressions.attributes = Some(parse_expression_handling(&v));
+    }
+    if let Ok(identifiers) = Reflect::get(options, &JsValue::from_str("expressionIdentifiers")) {
+        if let Value::Object(entries) = from_js_value(&identifiers) {
+            config.expressions.identifiers.extend(
+                entries
+                    .into_iter()
+                    .map(|(name, mode)| (name, parse_expression_handling(&mode.to_string()))),
+            );
+        }
+    }
+    if let Ok(data) = Reflect::get(options, &JsValue::from_str("data")) {
+        if let Value::Object(entries) = from_js_value(&data) {
+            config.data.extend(entries.into_iter().map(|(name, value)| (name, DataSource::Inline(value))));
+        }
+    }
+    if let Ok(targets) = Reflect::get(options, &JsValue::from_str("targets")) {
+        if let Value::Object(entries) = from_js_value(&targets) {
+            config.targets.extend(entries);
+        }
+    }
+    // `[{ component: "Internal*", action: "drop" }, { component: "Only", prop: "platform" }]`
+    if let Ok(filters) = Reflect::get(options, &JsValue::from_str("filters")) {
+        if Array::is_array(&filters) {
+            for filter in Array::from(&filters).iter() {
+                let action = match get_string(&filter, "action").as_deref() {
+                    None | Some("keep") => FilterAction::Keep,
+                    Some("drop") => FilterAction::Drop,
+                    Some(other) => return Err(format!("filter: unknown action {other:?}")),
+                };
+                config.filters.push(FilterRule {
+                    component: get_string(&filter, "component"),
+                    prop: get_string(&filter, "prop"),
+                    frontmatter: get_string(&filter, "frontmatter"),
+                    target: get_string(&filter, "target"),
+                    action,
+                });
+            }
+        }
+    }
+    if let Ok(variables) = Reflect::get(options, &JsValue::from_str("variables")) {
+        if let Value::Object(entries) = from_js_value(&variables) {
+            config.variables.extend(entries);
+        }
+    }
+    if let Some(v) = get_string(options, "templateEscape") {
+        config.options.template_escape = match v.as_str() {
+            "link_text" => Escape::LinkText,
+            "url" => Escape::Url,
+            "code" => Escape::Code,
+            "cell" => Escape::Cell,
+            "raw" => Escape::Raw,
+            _ => Escape::Markdown,
         };
     }
+    if let Some(v) = get_string(options, "inputFormat") {
+        config.options.input_format = if v == "html" { InputFormat::Html } else { InputFormat::Mdx };
+    }
+    let raw_text_tags = get_string_array(options, "rawTextTags");
+    if !raw_text_tags.is_empty() {
+        config.options.raw_text_tags = raw_text_tags;
+    }
+    if let Some(v) = get_string(options, "htmlSelector") {
+        config.html.selector = Some(v);
+    }
+    config.preset = get_string_array(options, "preset");
 
     // Parse components
     if let Ok(components_val) = Reflect::get(options, &JsValue::from_str("components")) {
@@ -89,11 +228,37 @@ fn parse_options(options: &JsValue) -> Result<(Config, HashMap<String, Function>
                 if let Some(template) = val.as_string() {
                     config.components.insert(
                         key_str,
-                        ComponentTransform { template },
+                        ComponentTransform {
+                            template,
+                            ..Default::default()
+                        },
                     );
                 } else if val.is_function() {
                     let func: Function = val.unchecked_into();
                     js_resolvers.insert(key_str, func);
+                } else if val.is_object() {
+                    // `{ mode: "html" }`, `{ template: "...", match: ["Note", "Tip*"] }`
+                    let mode = match get_string(&val, "mode").as_deref() {
+                        None | Some("template") => ComponentMode::Template,
+                        Some("drop") => ComponentMode::Drop,
+                        Some("unwrap") => ComponentMode::Unwrap,
+                        Some("html") => ComponentMode::Html,
+                        Some("raw") => ComponentMode::Raw,
+                        Some(other) => return Err(format!("component {key_str}: unknown mode {other:?}")),
+                    };
+                    let template = get_string(&val, "template");
+                    if mode == ComponentMode::Template && template.is_none() {
+                        return Err(format!("component {key_str} has no template (set `template` or `mode`)"));
+                    }
+                    config.components.insert(
+                        key_str,
+                        ComponentTransform {
+                            mode,
+                            template: template.unwrap_or_default(),
+                            matches: get_string_array(&val, "match"),
+                            ..Default::default()
+                        },
+                    );
                 }
             }
         }
@@ -135,6 +300,50 @@ fn parse_options(options: &JsValue) -> Result<(Config, HashMap<String, Function>
                 }
             }
 
+            // Headings
+            if let Ok(headings_val) = Reflect::get(&md_val, &JsValue::from_str("headings")) {
+                if !headings_val.is_undefined() && !headings_val.is_null() {
+                    config.markdown.headings = Some(HeadingRewrite {
+                        shift: Reflect::get(&headings_val, &JsValue::from_str("shift"))
+                            .ok()
+                            .and_then(|v| v.as_f64())
+                            .map_or(0, |n| n as i32),
+                        title: get_string(&headings_val, "title"),
+                        single_h1: get_bool(&headings_val, "singleH1").unwrap_or(false),
+                        setext_to_atx: get_bool(&headings_val, "setextToAtx").unwrap_or(false),
+                    });
+                }
+            }
+
+            if let Some(v) = get_string(&md_val, "headingIds") {
+                config.markdown.heading_ids = match v.as_str() {
+                    "keep" => HeadingIds::Keep,
+                    "strip" => HeadingIds::Strip,
+                    _ => HeadingIds::Anchor,
+                };
+            }
+
+            // Table of contents
+            if let Ok(toc_val) = Reflect::get(&md_val, &JsValue::from_str("toc")) {
+                if !toc_val.is_undefined() && !toc_val.is_null() && toc_val.as_bool() != Some(false) {
+                    let depth = |key: &str, default: u8| {
+                        Reflect::get(&toc_val, &JsValue::from_str(key))
+                            .ok()
+                            .and_then(|v| v.as_f64())
+                            .map_or(default, |n| n as u8)
+                    };
+                    config.markdown.toc = Some(TocRewrite {
+                        min_depth: depth("minDepth", 2),
+                        max_depth: depth("maxDepth", 3),
+                        position: match get_string(&toc_val, "position").as_deref() {
+                            Some("top") => TocPosition::Top,
+                            Some("afterFrontmatter") => TocPosition::AfterFrontmatter,
+                            _ => TocPosition::Marker,
+                        },
+                    });
+                }
+            }
+
             // HTML comments
             if let Some(v) = get_bool(&md_val, "stripHtmlComments") {
                 config.markdown.strip_html_comments = v;
@@ -147,6 +356,7 @@ fn parse_options(options: &JsValue) -> Result<(Config, HashMap<String, Function>
         }
     }
 
+    config.link().map_err(|e| e.message)?;
     Ok((config, js_resolvers))
 }
 